@@ -42,6 +42,10 @@ impl SmfReader {
         self.pointer = 0;
     }
 
+    pub fn from_bytes(data: Vec<u8>) -> SmfReader {
+        SmfReader{data, pointer: 0}
+    }
+
     pub fn read_from_file(filepath: &std::path::Path) -> Result<SmfReader> {
         use std::fs::File;
         use std::io::Read;
@@ -71,10 +75,7 @@ impl SmfReader {
     }
 
     pub fn next_bytes(&mut self, bytes: usize) -> Option<Vec<u8>> {
-        match self.next_bytes_ref(bytes) {
-            Some(x) => Some(x.to_vec()),
-            None => None
-        }
+        self.next_bytes_ref(bytes).map(|x| x.to_vec())
     }
 
     pub fn seek_bytes_ref(&self, bytes: usize) -> Option<&[u8]> {
@@ -85,10 +86,7 @@ impl SmfReader {
     }
 
     pub fn seek_bytes(&self, bytes: usize) -> Option<Vec<u8>> {
-        match self.seek_bytes_ref(bytes) {
-            Some(x) => Some(x.to_vec()),
-            None => None
-        }
+        self.seek_bytes_ref(bytes).map(|x| x.to_vec())
     }
 }
 
@@ -108,6 +106,22 @@ pub fn write_to_file(filepath: &std::path::Path, smf: &crate::types::event::SMF,
     smf.recalculate_length();
     let binary = smf.raw();
 
-    file.write(&binary)?;
+    file.write_all(&binary)?;
+    Ok(())
+}
+
+/// Same as write_to_file but omits status bytes repeated by running status
+pub fn write_to_file_running_status(filepath: &std::path::Path, smf: &crate::types::event::SMF, overwrite: bool) -> std::io::Result<()> {
+    use std::fs::OpenOptions;
+    use std::io::BufWriter;
+    use std::io::Write;
+
+    if !overwrite && filepath.exists() {
+        return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "Overwrite is disabled and file already exists"))
+    }
+    let file = OpenOptions::new().write(true).truncate(true).create(true).open(filepath)?;
+    let mut file = BufWriter::new(file);
+
+    file.write_all(&smf.raw_with_running_status())?;
     Ok(())
-}
\ No newline at end of file
+}
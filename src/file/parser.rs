@@ -56,7 +56,7 @@ impl SmfParser {
     pub fn next_chunk(&mut self) -> Option<Result<MidiChunk>> {
         if let Some(header) = self.reader.next_bytes(4) {
 
-            if header == &vec!['M' as u8, 'T' as u8, 'h' as u8, 'd' as u8][..] {
+            if header == b"MThd" {
                 // MThd length format tracks timebase
 
                 let length     = self.reader.next_bytes(4);
@@ -64,7 +64,7 @@ impl SmfParser {
                 let tracks     = self.reader.next_bytes(2);
                 let resolution = self.reader.next_bytes(2);
 
-                if vec![&length, &format, &tracks, &resolution].iter().all(|x| x.is_some()) {
+                if [&length, &format, &tracks, &resolution].iter().all(|x| x.is_some()) {
                     let length     = length.unwrap();
                     let format     = format.unwrap();
                     let tracks     = tracks.unwrap();
@@ -80,7 +80,7 @@ impl SmfParser {
                 }
                 
 
-            } else if header == &vec!['M' as u8, 'T' as u8, 'r' as u8, 'k' as u8][..] {
+            } else if header == b"MTrk" {
                 // MTrk length mtrk_events
 
                 if let Some(length) = self.reader.next_bytes(4) {
@@ -120,12 +120,7 @@ impl SmfParser {
             pairs.push(pair);
 
             match event {
-                MidiEvent::MetaEvent(me) => {
-                    match me {
-                        EndOfTrack => break,
-                        _ => continue,
-                    }
-                }
+                MidiEvent::MetaEvent(EndOfTrack) => break,
                 _ => continue,
             }
         }
@@ -163,7 +158,7 @@ impl SmfParser {
 
     fn parse_midi_event(&mut self) -> Result<MidiEvent> {
         let first_byte = self.reader.seek_bytes(1).ok_or(SmfError::new("unexpected None"))?[0];
-        let midi_event = if 0x80 <= first_byte && first_byte < 0xF0 {// Channel Messages
+        let midi_event = if (0x80..0xF0).contains(&first_byte) {// Channel Messages
             MidiEvent::MidiChannelMessage(self.parse_channel_message()?)
         } else if first_byte == 0xFF { // Meta Events
             MidiEvent::MetaEvent(self.parse_meta_event()?)
@@ -178,6 +173,7 @@ impl SmfParser {
 
     /// Returns true if the next token is EndOfTrack.
     /// This method does not increment SmfReader pointer.
+    #[allow(dead_code)]
     fn seek_if_track_end(&self) -> Result<bool> {
         // EndOfTrack FF 2F 00
         let three = self.reader.seek_bytes_ref(3);
@@ -231,7 +227,7 @@ impl SmfParser {
                 let channel = head - 0xA0;
                 let key = reader.next_bytes(1).ok_or(none_msg.clone())?[0];
                 let vel = reader.next_bytes(1).ok_or(none_msg.clone())?[0];
-                Some(NoteOn{channel, key, vel})
+                Some(PolyphonicKeyPressure{channel, key, vel})
             },
             0xB0 ..= 0xBF => { // TODO handling of ChannelModeMessage
                 let channel = head - 0xB0;
@@ -251,14 +247,13 @@ impl SmfParser {
             },
             0xE0 ..= 0xEF => {
                 let channel = head - 0xE0;
-                let msb = reader.next_bytes(1).ok_or(none_msg.clone())?[0];
                 let lsb = reader.next_bytes(1).ok_or(none_msg.clone())?[0];
-                Some(PitchBend{channel, msb, lsb})
+                let msb = reader.next_bytes(1).ok_or(none_msg.clone())?[0];
+                Some(PitchBend{channel, lsb, msb})
             },
             _ => None
         };
-        if cvm.is_some() {
-            let cvm = cvm.unwrap();
+        if let Some(cvm) = cvm {
             return Ok(MidiChannelMessage::ChannelVoiceMessage(cvm));
         }
 
@@ -299,9 +294,20 @@ impl SmfParser {
                 }
             },
 
+            0x20 => { // MIDI Channel Prefix
+                let length = self.parse_vlq()?;
+                if length != 1 {
+                    return Err(SmfError::new("invalid MIDI Channel Prefix length"));
+                }
+                let channel = self.reader.next_bytes(1).ok_or(none_msg.clone())?;
+                Ok(MIDIChannelPrefix{channel: channel[0]})
+            }
+
             0x21 => { // Specify Out Port
                 let length = self.parse_vlq()?;
-                assert_eq!(length, 1);
+                if length != 1 {
+                    return Err(SmfError::new("invalid Specify Out Port length"));
+                }
                 let port = self.reader.next_bytes(1).ok_or(none_msg.clone())?;
                 Ok(SpecifyOutPort{port: port[0]})
             }
@@ -326,7 +332,7 @@ impl SmfParser {
                 assert_eq!(length, 5);
                 let data = self.reader.next_bytes(5).ok_or(none_msg.clone())?;
                 let smpte = ((data[0] as u32) << 24) + ((data[1] as u32) << 16) + ((data[2] as u32) << 8) + (data[3] as u32);
-                let frame = data[5] as u8;
+                let frame = data[4];
                 Ok(SMPTEOffset{smpte, frame})
             },
 
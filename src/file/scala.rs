@@ -0,0 +1,187 @@
+use super::filerw::SmfError;
+use crate::types::tuning::Tuning;
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Scala scale (.scl). Degree 0 (1/1) is implicit; the last pitch is the period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalaScale {
+    pub description: String,
+    /// Pitches of degrees 1 to n in cents
+    pub pitches: std::vec::Vec<f64>,
+}
+
+/// Scala keyboard mapping (.kbm)
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMapping {
+    pub first_key: u8,
+    pub last_key: u8,
+    /// Key where degree 0 (the first mapping entry) is placed
+    pub middle_key: u8,
+    pub reference_key: u8,
+    pub reference_frequency: f64,
+    /// Scale degree which is the formal octave of the mapping
+    pub octave_degree: usize,
+    /// Scale degree of each key in the pattern. Empty means a linear mapping. None is an unmapped key.
+    pub mapping: std::vec::Vec<Option<usize>>,
+}
+
+/// Non-comment lines of a Scala file
+fn data_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().filter(|line| !line.starts_with('!'))
+}
+
+/// First whitespace-delimited token of a line
+fn token(line: &str) -> &str {
+    line.split_whitespace().next().unwrap_or("")
+}
+
+/// Parses a pitch: cents if it contains a period, otherwise a ratio or an integer
+fn parse_pitch(line: &str) -> Result<f64> {
+    let text = token(line);
+    let invalid = || SmfError::new(&format!("invalid scala pitch: {}", line.trim()));
+    if text.contains('.') {
+        return text.parse::<f64>().map_err(|_| invalid());
+    }
+    let (num, den) = match text.find('/') {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => (text, "1"),
+    };
+    let num: f64 = num.parse::<u64>().map_err(|_| invalid())? as f64;
+    let den: f64 = den.parse::<u64>().map_err(|_| invalid())? as f64;
+    if num <= 0.0 || den <= 0.0 {
+        return Err(invalid());
+    }
+    Ok(1200.0 * (num / den).log2())
+}
+
+impl ScalaScale {
+    pub fn parse(text: &str) -> Result<ScalaScale> {
+        let mut lines = data_lines(text);
+        let description = lines.next().ok_or_else(|| SmfError::new("empty scala file"))?.trim().to_string();
+        let count = lines.next()
+            .and_then(|line| token(line).parse::<usize>().ok())
+            .ok_or_else(|| SmfError::new("missing number of notes in scala file"))?;
+        let pitches = lines.filter(|line| !line.trim().is_empty()).take(count).map(parse_pitch).collect::<Result<std::vec::Vec<f64>>>()?;
+        if pitches.len() != count {
+            return Err(SmfError::new(&format!("scala file declares {} notes but has {}", count, pitches.len())));
+        }
+        Ok(ScalaScale {description, pitches})
+    }
+
+    pub fn read_file(path: &std::path::Path) -> Result<ScalaScale> {
+        let text = std::fs::read_to_string(path).map_err(|e| SmfError::new(&e.to_string()))?;
+        ScalaScale::parse(&text)
+    }
+
+    /// Equal division of the octave into n steps
+    pub fn equal_temperament(steps: usize) -> ScalaScale {
+        ScalaScale {
+            description: format!("{} equal divisions of the octave", steps),
+            pitches: (1..=steps).map(|i| 1200.0 * i as f64 / steps as f64).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pitches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pitches.is_empty()
+    }
+
+    /// Period in cents (usually an octave)
+    pub fn period(&self) -> f64 {
+        self.pitches.last().copied().unwrap_or(1200.0)
+    }
+
+    /// Pitch of any degree in cents, extending the scale by its period
+    pub fn degree_cents(&self, degree: i64) -> f64 {
+        let n = self.pitches.len() as i64;
+        if n == 0 {
+            return 0.0;
+        }
+        let period = degree.div_euclid(n);
+        let index = degree.rem_euclid(n);
+        let within = if index == 0 {0.0} else {self.pitches[index as usize - 1]};
+        period as f64 * self.period() + within
+    }
+
+    /// Frequencies of every key
+    pub fn tuning(&self, mapping: &KeyboardMapping) -> Tuning {
+        // Pitch of a key in cents relative to degree 0 on the middle key
+        let cents = |key: u8| -> Option<f64> {
+            let offset = key as i64 - mapping.middle_key as i64;
+            if mapping.mapping.is_empty() {
+                return Some(self.degree_cents(offset));
+            }
+            let size = mapping.mapping.len() as i64;
+            let degree = mapping.mapping[offset.rem_euclid(size) as usize]?;
+            let octave = self.degree_cents(mapping.octave_degree as i64);
+            Some(offset.div_euclid(size) as f64 * octave + self.degree_cents(degree as i64))
+        };
+        // An unmapped reference key is measured linearly from the middle key
+        let reference = cents(mapping.reference_key)
+            .unwrap_or_else(|| self.degree_cents(mapping.reference_key as i64 - mapping.middle_key as i64));
+        let frequencies = (0..128u8)
+            .map(|key| {
+                if key < mapping.first_key || key > mapping.last_key {
+                    return None;
+                }
+                cents(key).map(|c| mapping.reference_frequency * 2f64.powf((c - reference) / 1200.0))
+            })
+            .collect();
+        Tuning {frequencies}
+    }
+}
+
+impl KeyboardMapping {
+    /// Linear mapping with degree 0 on middle C (261.6256 Hz)
+    pub fn new() -> KeyboardMapping {
+        KeyboardMapping {
+            first_key: 0,
+            last_key: 127,
+            middle_key: 60,
+            reference_key: 60,
+            reference_frequency: crate::types::tuning::key_to_frequency(60.0),
+            octave_degree: 0,
+            mapping: vec![],
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<KeyboardMapping> {
+        let mut lines = data_lines(text).filter(|line| !line.trim().is_empty()).map(token);
+        let mut next = |name: &str| lines.next().ok_or_else(|| SmfError::new(&format!("missing {} in keyboard mapping", name)));
+        let invalid = |name: &str| SmfError::new(&format!("invalid {} in keyboard mapping", name));
+        let size: usize = next("map size")?.parse().map_err(|_| invalid("map size"))?;
+        let first_key: u8 = next("first key")?.parse().map_err(|_| invalid("first key"))?;
+        let last_key: u8 = next("last key")?.parse().map_err(|_| invalid("last key"))?;
+        let middle_key: u8 = next("middle key")?.parse().map_err(|_| invalid("middle key"))?;
+        let reference_key: u8 = next("reference key")?.parse().map_err(|_| invalid("reference key"))?;
+        let reference_frequency: f64 = next("reference frequency")?.parse().map_err(|_| invalid("reference frequency"))?;
+        let octave_degree: usize = next("octave degree")?.parse().map_err(|_| invalid("octave degree"))?;
+        let mut mapping = vec![];
+        for _ in 0..size {
+            // Trailing entries may be omitted; they are unmapped
+            match lines.next() {
+                Some("x") | Some("X") | None => mapping.push(None),
+                Some(degree) => mapping.push(Some(degree.parse().map_err(|_| invalid("key mapping"))?)),
+            }
+        }
+        if first_key > 127 || last_key > 127 || middle_key > 127 || reference_key > 127 || reference_frequency <= 0.0 {
+            return Err(SmfError::new("keyboard mapping out of range"));
+        }
+        Ok(KeyboardMapping {first_key, last_key, middle_key, reference_key, reference_frequency, octave_degree, mapping})
+    }
+
+    pub fn read_file(path: &std::path::Path) -> Result<KeyboardMapping> {
+        let text = std::fs::read_to_string(path).map_err(|e| SmfError::new(&e.to_string()))?;
+        KeyboardMapping::parse(&text)
+    }
+}
+
+impl Default for KeyboardMapping {
+    fn default() -> Self {
+        KeyboardMapping::new()
+    }
+}
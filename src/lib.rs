@@ -1,10 +1,14 @@
 pub mod types;
 pub mod file;
+pub mod ops;
+pub mod playback;
+pub mod wire;
 
 #[cfg(test)]
 mod tests {
     use super::types::message;
     use super::file::{filerw, parser};
+    use super::types::message::SmfElement;
 
     #[test]
     fn from_vlq_1() {
@@ -63,7 +67,6 @@ mod tests {
                                         panic!("Not MThd chunk!");
                                     }
                                 }
-                                ()
                             },
                             Err(e) => {
                                 panic!("{}", e);
@@ -83,6 +86,42 @@ mod tests {
 
     }
 
+    #[test]
+    fn parse_polyphonic_key_pressure() {
+        use super::types::event::MidiEvent;
+        use super::types::message::{MidiChannelMessage, ChannelVoiceMessage};
+
+        let reader = filerw::SmfReader::from_bytes(vec![0x00, 0xA1, 60, 50]);
+        let pair = parser::SmfParser::new(reader).parse_mtrk_event().unwrap();
+        assert_eq!(pair.event_copy(), MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(
+            ChannelVoiceMessage::PolyphonicKeyPressure{channel: 1, key: 60, vel: 50})));
+    }
+
+    #[test]
+    fn parse_rejects_bad_meta_length() {
+        for meta in [0x20, 0x21] {
+            let reader = filerw::SmfReader::from_bytes(vec![0x00, 0xFF, meta, 0x02, 0x00, 0x00]);
+            assert!(parser::SmfParser::new(reader).parse_mtrk_event().is_err());
+        }
+    }
+
+    #[test]
+    fn pitch_bend_wire_order() {
+        use super::types::event::MidiEvent;
+        use super::types::message::{MidiChannelMessage, ChannelVoiceMessage, SmfElement};
+
+        // 0x2001: LSB 0x01 is sent before MSB 0x40
+        let bend = ChannelVoiceMessage::pitch_bend(2, 0x2001);
+        assert_eq!(bend.raw(), vec![0xE2, 0x01, 0x40]);
+
+        let mut bytes = vec![0x00];
+        bytes.extend(bend.raw());
+        let reader = filerw::SmfReader::from_bytes(bytes);
+        let pair = parser::SmfParser::new(reader).parse_mtrk_event().unwrap();
+        assert_eq!(bend.pitch_bend_value(), Some(0x2001));
+        assert_eq!(pair.event_copy(), MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(bend)));
+    }
+
     #[test]
     #[ignore]
     fn midi_parse_all() {
@@ -134,4 +173,1081 @@ mod tests {
             }
         }
     }
+
+    fn note_on(channel: u8, key: u8, vel: u8) -> super::types::event::MidiEvent {
+        use super::types::message::{MidiChannelMessage, ChannelVoiceMessage};
+        super::types::event::MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOn{channel, key, vel}))
+    }
+
+    fn note_off(channel: u8, key: u8) -> super::types::event::MidiEvent {
+        use super::types::message::{MidiChannelMessage, ChannelVoiceMessage};
+        super::types::event::MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOff{channel, key, vel: 0}))
+    }
+
+    fn end_of_track() -> super::types::event::MidiEvent {
+        super::types::event::MidiEvent::MetaEvent(super::types::message::MetaEvent::EndOfTrack)
+    }
+
+    fn smf_of(resolution: u16, tracks: Vec<Vec<(u32, super::types::event::MidiEvent)>>) -> super::types::event::SMF {
+        use super::types::event::{SMF, HeaderChunk, TrackChunk};
+        let header = HeaderChunk{length: 6, format: 1, tracks: tracks.len() as u16, resolution};
+        SMF::new(header, tracks.into_iter().map(TrackChunk::from_absolute).collect())
+    }
+
+    #[test]
+    fn track_insert_remove_event() {
+        use super::types::event::TrackChunk;
+
+        let mut track = TrackChunk::from_absolute(vec![
+            (0, note_on(0, 60, 100)),
+            (480, note_off(0, 60)),
+            (960, end_of_track()),
+        ]);
+        let index = track.insert_event(240, note_on(0, 64, 100));
+        assert_eq!(index, 1);
+        assert_eq!(track.absolute_ticks(), vec![0, 240, 480, 960]);
+        assert_eq!(track[2].time(), 240);
+
+        // Inserting after the end moves EndOfTrack
+        let index = track.insert_event(1200, note_off(0, 64));
+        assert_eq!(index, 3);
+        assert_eq!(track.absolute_ticks(), vec![0, 240, 480, 1200, 1200]);
+        assert!(track[4].is_end_of_track());
+
+        track.remove_event(1);
+        assert_eq!(track.absolute_ticks(), vec![0, 480, 1200, 1200]);
+        assert_eq!(track.length as usize, track.events.iter().map(|p| p.raw().len()).sum::<usize>());
+    }
+
+    #[test]
+    fn smf_add_remove_track() {
+        use super::types::event::TrackChunk;
+
+        let mut smf = smf_of(480, vec![vec![(0, end_of_track())]]);
+        smf.add_track(TrackChunk::from_absolute(vec![(0, end_of_track())])).unwrap();
+        assert_eq!(smf.header().tracks, 2);
+        smf.remove_track(0);
+        assert_eq!(smf.header().tracks, 1);
+        assert_eq!(smf.tracks().len(), 1);
+
+        smf.set_tracks(vec![TrackChunk::new(vec![]); u16::MAX as usize]).unwrap();
+        assert!(smf.add_track(TrackChunk::new(vec![])).is_err());
+        assert_eq!(smf.header().tracks, u16::MAX);
+    }
+
+    #[test]
+    fn transpose_skips_drums_and_drops() {
+        use super::ops::transpose::{TransposeOptions, OutOfRange, transpose_key_signature};
+
+        let mut smf = smf_of(480, vec![vec![
+            (0, note_on(0, 120, 100)),
+            (0, note_on(0, 60, 100)),
+            (0, note_on(9, 36, 100)),
+            (480, note_off(0, 120)),
+            (480, note_off(0, 60)),
+            (480, end_of_track()),
+        ]]);
+        let mut options = TransposeOptions::new(12);
+        options.out_of_range = OutOfRange::Drop;
+        smf.transpose(&options);
+
+        let track = &smf.tracks()[0];
+        assert_eq!(track.to_absolute(), vec![
+            (0, note_on(0, 72, 100)),
+            (0, note_on(9, 36, 100)),
+            (480, note_off(0, 72)),
+            (480, end_of_track()),
+        ]);
+
+        // C major + 2 semitones = D major (2 sharps), F major (1 flat) - 1 = E major (4 sharps)
+        assert_eq!(transpose_key_signature(0, 2), 2);
+        assert_eq!(transpose_key_signature(0xFF, -1), 4);
+    }
+
+    #[test]
+    fn note_pairing() {
+        let smf = smf_of(480, vec![vec![
+            (0, note_on(0, 60, 100)),
+            (10, note_on(0, 60, 90)),
+            (100, note_off(0, 60)),
+            (200, note_on(0, 60, 0)),
+            (300, note_on(1, 62, 80)),
+            (400, end_of_track()),
+        ]]);
+        let notes = smf.tracks()[0].notes();
+        assert_eq!(notes.len(), 3);
+        assert_eq!((notes[0].start, notes[0].end), (0, Some(100)));
+        assert_eq!((notes[1].start, notes[1].end), (10, Some(200)));
+        assert_eq!((notes[2].start, notes[2].end), (300, None));
+    }
+
+    #[test]
+    fn quantize_notes() {
+        use super::ops::quantize::{Grid, QuantizeOptions};
+
+        let mut smf = smf_of(480, vec![vec![
+            (10, note_on(0, 60, 100)),
+            (250, note_off(0, 60)),
+            (470, note_on(0, 62, 100)),
+            (700, note_off(0, 62)),
+            (960, end_of_track()),
+        ]]);
+        // 1/8 grid (240 ticks)
+        let original = smf.clone();
+        smf.quantize(&QuantizeOptions::new(Grid::new(8))).unwrap();
+        let notes = smf.tracks()[0].notes();
+        assert_eq!((notes[0].start, notes[0].end), (0, Some(240)));
+        assert_eq!((notes[1].start, notes[1].end), (480, Some(710)));
+        assert!(smf.quantize(&QuantizeOptions::new(Grid::new(0))).is_err());
+
+        // Ends are snapped even when the start is outside the window
+        let mut smf = original;
+        let mut options = QuantizeOptions::new(Grid::new(8));
+        options.window = Some(0.05);
+        options.quantize_ends = true;
+        smf.quantize(&options).unwrap();
+        let notes = smf.tracks()[0].notes();
+        assert_eq!((notes[0].start, notes[0].end), (0, Some(240)));
+        assert_eq!((notes[1].start, notes[1].end), (480, Some(700)));
+        let mut smf = smf_of(480, vec![vec![(300, note_on(0, 60, 100)), (475, note_off(0, 60)), (480, end_of_track())]]);
+        smf.quantize(&options).unwrap();
+        assert_eq!(smf.tracks()[0].notes()[0].end, Some(480));
+        assert_eq!(smf.tracks()[0].notes()[0].start, 300);
+
+        // swing moves the offbeat
+        let mut grid = Grid::new(8);
+        grid.swing = 75.0;
+        assert_eq!(grid.nearest(480, 300), 360);
+        assert_eq!(Grid::triplet(8).step(480), 160.0);
+    }
+
+    #[test]
+    fn rescale_resolution_without_drift() {
+        let mut smf = smf_of(960, vec![vec![
+            (1, note_on(0, 60, 100)),
+            (2, note_off(0, 60)),
+            (3, note_on(0, 60, 100)),
+            (961, note_off(0, 60)),
+            (1921, end_of_track()),
+        ]]);
+        smf.rescale_resolution(96).unwrap();
+        assert_eq!(smf.resolution(), 96);
+        assert_eq!(smf.tracks()[0].absolute_ticks(), vec![0, 0, 0, 96, 192]);
+        assert!(smf.rescale_resolution(0).is_err());
+        assert!(smf.tracks()[0].clone().rescale_resolution(0, 480).is_err());
+
+        let mut long = smf_of(1, vec![vec![(u32::MAX / 2, end_of_track())]]);
+        assert!(long.rescale_resolution(960).is_err());
+        assert_eq!(long.resolution(), 1);
+    }
+
+    fn control_change(channel: u8, cc: u8, value: u8) -> super::types::event::MidiEvent {
+        use super::types::message::{MidiChannelMessage, ChannelVoiceMessage};
+        super::types::event::MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ControlChange{channel, cc, value}))
+    }
+
+    fn set_tempo(tempo: u32) -> super::types::event::MidiEvent {
+        super::types::event::MidiEvent::MetaEvent(super::types::message::MetaEvent::SetTempo{tempo})
+    }
+
+    #[test]
+    fn tempo_map_seconds() {
+        let smf = smf_of(480, vec![vec![
+            (0, set_tempo(500_000)),
+            (960, set_tempo(1_000_000)),
+            (1920, end_of_track()),
+        ]]);
+        let map = smf.tempo_map();
+        assert_eq!(map.tick_to_seconds(960), 1.0);
+        assert_eq!(map.tick_to_seconds(1920), 3.0);
+        assert_eq!(map.seconds_to_tick(2.0), 1440);
+        assert_eq!(smf.duration_seconds(), 3.0);
+    }
+
+    #[test]
+    fn slice_carries_state_and_closes_notes() {
+        let smf = smf_of(480, vec![vec![
+            (0, control_change(0, 7, 100)),
+            (0, note_on(0, 60, 100)),
+            (100, control_change(0, 7, 80)),
+            (480, note_off(0, 60)),
+            (480, note_on(0, 62, 100)),
+            (1200, note_off(0, 62)),
+            (1920, end_of_track()),
+        ]]);
+        // The note sounding at 240 is retriggered
+        let sliced = smf.slice_ticks(240, 960);
+        assert_eq!(sliced.tracks()[0].to_absolute(), vec![
+            (0, control_change(0, 7, 80)),
+            (0, note_on(0, 60, 100)),
+            (240, note_off(0, 60)),
+            (240, note_on(0, 62, 100)),
+            (720, note_off(0, 62)),
+            (720, end_of_track()),
+        ]);
+
+        // The note ending exactly at the slice start leaves nothing behind
+        let sliced = smf.slice_ticks(480, 960);
+        assert_eq!(sliced.tracks()[0].to_absolute(), vec![
+            (0, control_change(0, 7, 80)),
+            (0, note_on(0, 62, 100)),
+            (480, note_off(0, 62)),
+            (480, end_of_track()),
+        ]);
+    }
+
+    #[test]
+    fn insert_and_delete_time() {
+        let mut smf = smf_of(480, vec![vec![
+            (0, note_on(0, 60, 100)),
+            (480, note_off(0, 60)),
+            (480, note_on(0, 62, 100)),
+            (600, note_off(0, 62)),
+            (960, end_of_track()),
+        ]]);
+        smf.insert_time(480, 240);
+        assert_eq!(smf.tracks()[0].absolute_ticks(), vec![0, 720, 720, 840, 1200]);
+        let mut far = smf.clone();
+        far.insert_time(1000, u32::MAX);
+        assert_eq!(far.tracks()[0].end_tick(), u32::MAX);
+        smf.delete_time(480, 960);
+        assert_eq!(smf.tracks()[0].to_absolute(), vec![
+            (0, note_on(0, 60, 100)),
+            (480, note_off(0, 60)),
+            (720, end_of_track()),
+        ]);
+    }
+
+    fn track_name(name: &str) -> super::types::event::MidiEvent {
+        super::types::event::MidiEvent::MetaEvent(super::types::message::MetaEvent::SequenceTrackName{length: name.len() as u32, text: name.as_bytes().to_vec()})
+    }
+
+    #[test]
+    fn concat_by_name_with_rescale() {
+        use super::ops::concat::TrackMatching;
+
+        let first = smf_of(480, vec![
+            vec![(0, track_name("piano")), (0, set_tempo(1_000_000)), (0, note_on(0, 60, 100)), (480, note_off(0, 60)), (960, end_of_track())],
+            vec![(0, track_name("bass")), (480, end_of_track())],
+        ]);
+        let second = smf_of(960, vec![
+            vec![(0, track_name("bass")), (0, note_on(1, 40, 100)), (960, note_off(1, 40)), (960, end_of_track())],
+        ]);
+        let joined = super::types::event::SMF::concat(&[first, second], TrackMatching::ByName).unwrap();
+        assert_eq!(joined.header().tracks, 2);
+        assert_eq!(joined.tracks()[1].to_absolute(), vec![
+            (0, track_name("bass")),
+            (960, note_on(1, 40, 100)),
+            (1440, note_off(1, 40)),
+            (1440, end_of_track()),
+        ]);
+        // The second file plays at its own default tempo
+        assert_eq!(joined.tempo_map().changes(), &[(0, 1_000_000), (960, 500_000)]);
+
+        // Ticks past u32::MAX are rejected
+        let mut long = smf_of(480, vec![vec![(u32::MAX - 10, end_of_track())]]);
+        let tail = smf_of(480, vec![vec![(0, note_on(0, 60, 100)), (20, note_off(0, 60)), (20, end_of_track())]]);
+        assert!(long.append(&tail, TrackMatching::ByIndex).is_err());
+        assert_eq!(long.end_tick(), u32::MAX - 10);
+    }
+
+    fn program_change(channel: u8, pc: u8) -> super::types::event::MidiEvent {
+        use super::types::message::{MidiChannelMessage, ChannelVoiceMessage};
+        super::types::event::MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ProgramChange{channel, pc}))
+    }
+
+    #[test]
+    fn split_format0_by_channel() {
+        use super::ops::split::SplitBy;
+
+        let mut smf = smf_of(480, vec![vec![
+            (0, set_tempo(500_000)),
+            (0, program_change(0, 0)),
+            (0, program_change(1, 33)),
+            (0, note_on(0, 60, 100)),
+            (0, note_on(1, 40, 100)),
+            (0, note_on(9, 36, 100)),
+            (480, note_off(0, 60)),
+            (480, note_off(1, 40)),
+            (480, note_off(9, 36)),
+            (960, end_of_track()),
+        ]]);
+        smf.set_format(0);
+        smf.split_tracks(SplitBy::Channel).unwrap();
+
+        assert_eq!(smf.format(), 1);
+        assert_eq!(smf.header().tracks, 4);
+        assert_eq!(smf.tracks()[0].to_absolute(), vec![(0, set_tempo(500_000)), (960, end_of_track())]);
+        let names: Vec<_> = smf.tracks()[1..].iter().map(|t| t.name().unwrap()).collect();
+        assert_eq!(names, vec!["Ch.1 Acoustic Grand Piano", "Ch.2 Electric Bass (finger)", "Ch.10 Drums"]);
+        assert_eq!(smf.tracks()[2].len(), 5);
+
+        // Track meta events are kept on every track split from a named track
+        let instrument = super::types::event::MidiEvent::MetaEvent(super::types::message::MetaEvent::InstrumentName{length: 4, text: b"Keys".to_vec()});
+        let mut smf = smf_of(480, vec![
+            vec![(0, set_tempo(500_000)), (0, end_of_track())],
+            vec![(0, track_name("upper")), (0, instrument.clone()), (0, note_on(0, 60, 100)), (0, note_on(1, 40, 100)), (480, end_of_track())],
+        ]);
+        smf.split_tracks(SplitBy::Channel).unwrap();
+        assert_eq!(smf.header().tracks, 3);
+        assert_eq!(smf.tracks()[1].name().unwrap(), "upper: Ch.1");
+        assert_eq!(smf.tracks()[2].name().unwrap(), "upper: Ch.2");
+        assert!(smf.tracks()[1..].iter().all(|t| t.to_absolute().contains(&(0, instrument.clone()))));
+    }
+
+    #[test]
+    fn remap_and_filter_channels() {
+        use super::ops::channel::ChannelMap;
+        use super::types::message::MetaEvent;
+        use super::types::event::MidiEvent;
+
+        let prefix = |channel| MidiEvent::MetaEvent(MetaEvent::MIDIChannelPrefix{channel});
+        let mut smf = smf_of(480, vec![vec![
+            (0, prefix(0)),
+            (0, note_on(0, 60, 100)),
+            (0, note_on(1, 62, 100)),
+            (480, note_off(0, 60)),
+            (480, note_off(1, 62)),
+            (480, end_of_track()),
+        ]]);
+        let mut map = ChannelMap::identity();
+        map.set(0, 3).set(1, 0);
+        smf.remap_channels(&map);
+        smf.drop_channels(&[0]);
+        assert_eq!(smf.tracks()[0].to_absolute(), vec![
+            (0, prefix(3)),
+            (0, note_on(3, 60, 100)),
+            (480, note_off(3, 60)),
+            (480, end_of_track()),
+        ]);
+        assert_eq!(ChannelMap::from_table([0x13; 16]).get(5), 3);
+    }
+
+    #[test]
+    fn velocity_curves() {
+        use super::ops::velocity::VelocityCurve;
+
+        assert_eq!(VelocityCurve::Scale{factor: 0.5, offset: 10}.apply(100), 60);
+        assert_eq!(VelocityCurve::Scale{factor: 0.0, offset: 0}.apply(100), 1);
+        assert_eq!(VelocityCurve::Compress{pivot: 64, ratio: 0.5}.apply(124), 94);
+        assert_eq!(VelocityCurve::power(1.0).apply(77), 77);
+
+        let mut smf = smf_of(480, vec![vec![
+            (0, note_on(0, 60, 100)),
+            (0, note_on(1, 60, 100)),
+            (480, note_on(0, 60, 0)),
+            (480, end_of_track()),
+        ]]);
+        smf.apply_velocity(&VelocityCurve::Fixed(64), Some(&[0]));
+        assert_eq!(smf.tracks()[0].to_absolute()[..3], [
+            (0, note_on(0, 60, 64)),
+            (0, note_on(1, 60, 100)),
+            (480, note_on(0, 60, 0)),
+        ]);
+    }
+
+    #[test]
+    fn change_speed_and_fit_duration() {
+        use super::ops::stretch::StretchMode;
+
+        let original = smf_of(480, vec![vec![
+            (0, note_on(0, 60, 100)),
+            (960, note_off(0, 60)),
+            (960, end_of_track()),
+        ]]);
+        assert_eq!(original.duration_seconds(), 1.0);
+
+        let mut smf = original.clone();
+        smf.change_speed(0.5, StretchMode::Tempo).unwrap();
+        assert_eq!(smf.tracks()[0].to_absolute()[0], (0, set_tempo(1_000_000)));
+        assert_eq!(smf.duration_seconds(), 2.0);
+
+        let mut smf = original.clone();
+        smf.fit_duration(4.0, StretchMode::Ticks).unwrap();
+        assert_eq!(smf.tracks()[0].absolute_ticks(), vec![0, 3840, 3840]);
+
+        // 500000 us per beat at 1/100 speed does not fit in 24 bits: the file is left as it was
+        let mut smf = original.clone();
+        assert!(smf.fit_duration(100.0, StretchMode::Tempo).is_err());
+        assert_eq!(smf, original);
+    }
+
+    #[test]
+    fn humanize_is_reproducible_and_ordered() {
+        use super::ops::humanize::HumanizeOptions;
+
+        let mut events = Vec::new();
+        for i in 0..32 {
+            events.push((i * 120, note_on(0, 60, 100)));
+            events.push((i * 120 + 120, note_off(0, 60)));
+        }
+        events.push((32 * 120, end_of_track()));
+        let original = smf_of(480, vec![events]);
+
+        let mut options = HumanizeOptions::new(42);
+        options.timing = 30;
+        options.duration = 30;
+        options.velocity = 20;
+
+        let mut a = original.clone();
+        let mut b = original.clone();
+        a.humanize(&options);
+        b.humanize(&options);
+        assert_eq!(a, b);
+        assert_ne!(a, original);
+
+        let notes = a.tracks()[0].notes();
+        assert_eq!(notes.len(), 32);
+        for pair in notes.windows(2) {
+            assert!(pair[0].start < pair[0].end.unwrap());
+            assert!(pair[0].end.unwrap() <= pair[1].start);
+        }
+    }
+
+    #[test]
+    fn cleanup_notes_report() {
+        let mut smf = smf_of(480, vec![vec![
+            (0, note_off(0, 50)),
+            (0, note_on(0, 60, 100)),
+            (0, note_on(0, 60, 90)),
+            (100, note_off(0, 60)),
+            (200, note_off(0, 60)),
+            (240, note_on(0, 62, 100)),
+            (240, note_off(0, 62)),
+            (300, note_on(0, 64, 100)),
+            (400, note_on(0, 64, 100)),
+            (500, note_off(0, 64)),
+            (600, note_off(0, 64)),
+            (700, note_on(0, 65, 100)),
+            (960, end_of_track()),
+        ]]);
+        let report = smf.cleanup_notes();
+        assert_eq!(report.orphan_note_offs, 1);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.zero_length, 1);
+        assert_eq!(report.overlapping, 1);
+        assert_eq!(report.unterminated, 1);
+        assert_eq!(smf.tracks()[0].to_absolute(), vec![
+            (0, note_on(0, 60, 100)),
+            (200, note_off(0, 60)),
+            (300, note_on(0, 64, 100)),
+            (400, note_off(0, 64)),
+            (400, note_on(0, 64, 100)),
+            (600, note_off(0, 64)),
+            (700, note_on(0, 65, 100)),
+            (960, note_off(0, 65)),
+            (960, end_of_track()),
+        ]);
+        assert_eq!(smf.cleanup_notes().total(), 0);
+    }
+
+    #[test]
+    fn optimize_and_running_status() {
+        let mut smf = smf_of(480, vec![
+            vec![(0, set_tempo(500_000)), (480, set_tempo(500_000)), (960, end_of_track())],
+            vec![(0, track_name("empty")), (0, end_of_track())],
+            vec![
+                (0, control_change(0, 7, 100)),
+                (0, program_change(0, 5)),
+                (0, note_on(0, 60, 100)),
+                (0, note_on(0, 64, 100)),
+                (240, control_change(0, 7, 100)),
+                (240, control_change(1, 7, 100)),
+                (480, note_on(0, 60, 0)),
+                (480, note_on(0, 64, 0)),
+                (480, program_change(0, 5)),
+                (960, end_of_track()),
+            ],
+        ]);
+        let report = smf.optimize();
+        assert_eq!((report.tempos, report.controllers, report.programs, report.empty_tracks), (1, 1, 1, 1));
+        assert_eq!(smf.header().tracks, 2);
+        assert_eq!(smf.tracks()[1].absolute_ticks(), vec![0, 0, 0, 0, 240, 480, 480, 960]);
+
+        // Read back the running status output
+        let raw = smf.raw_with_running_status();
+        let reader = filerw::SmfReader::from_bytes(raw.clone());
+        let parsed = parser::SmfParser::new(reader).read_all().unwrap();
+        assert!(raw.len() < smf.raw().len());
+        assert_eq!(parsed.tracks()[1].to_absolute(), smf.tracks()[1].to_absolute());
+    }
+
+    #[test]
+    fn optimize_keeps_bank_changes_and_ports_apart() {
+        let specify_out_port = |port| super::types::event::MidiEvent::MetaEvent(super::types::message::MetaEvent::SpecifyOutPort{port});
+        let mut smf = smf_of(480, vec![
+            vec![
+                (0, specify_out_port(0)),
+                (0, program_change(0, 5)),
+                (240, control_change(0, 0, 1)),
+                (240, program_change(0, 5)),
+                (480, control_change(0, 0, 1)),
+                (480, program_change(0, 5)),
+                (960, end_of_track()),
+            ],
+            vec![
+                (0, specify_out_port(1)),
+                (0, program_change(0, 5)),
+                (960, end_of_track()),
+            ],
+        ]);
+        let report = smf.optimize();
+        assert_eq!((report.controllers, report.programs), (1, 1));
+        assert_eq!(smf.tracks()[0].absolute_ticks(), vec![0, 0, 240, 240, 960]);
+        assert_eq!(smf.tracks()[1].absolute_ticks(), vec![0, 0, 960]);
+    }
+
+    #[test]
+    fn query_and_edit_events() {
+        use super::ops::query::{EventFilter, EventKind};
+
+        let mut smf = smf_of(480, vec![
+            vec![(0, set_tempo(500_000)), (960, end_of_track())],
+            vec![
+                (0, control_change(0, 7, 100)),
+                (0, note_on(0, 60, 100)),
+                (0, note_on(9, 36, 90)),
+                (480, note_on(0, 72, 40)),
+                (960, note_off(0, 60)),
+                (960, note_off(0, 72)),
+                (960, end_of_track()),
+            ],
+        ]);
+
+        let filter = EventFilter::new().kind(EventKind::NoteOn).channel(0).keys(60..=71);
+        let found = smf.query(&filter);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].location.track, found[0].location.index, found[0].tick), (1, 1, 0));
+
+        let tempo = smf.query(&EventFilter::new().kind(EventKind::Meta(Some(0x51))));
+        assert_eq!(tempo.len(), 1);
+
+        let soft = EventFilter::new().kind(EventKind::NoteOn).velocities(0..=50).ticks(0..960);
+        assert_eq!(smf.remove_matching(&soft), 1);
+        assert_eq!(smf.tracks()[1].absolute_ticks(), vec![0, 0, 0, 960, 960, 960]);
+
+        let cc = EventFilter::new().kind(EventKind::ControlChange(Some(7)));
+        smf.edit_matching(&cc, |event| *event = control_change(0, 7, 64));
+        assert_eq!(smf.event_at(found[0].location), Some(&note_on(0, 60, 100)));
+        assert_eq!(smf.tracks()[1][0].event(), &control_change(0, 7, 64));
+    }
+
+    #[test]
+    fn channel_state_replay() {
+        use super::types::message::{MidiChannelMessage, ChannelVoiceMessage};
+        use super::types::event::MidiEvent;
+
+        let bend = MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::pitch_bend(0, 10000)));
+        let smf = smf_of(480, vec![vec![
+            (0, control_change(0, 0, 1)),
+            (0, program_change(0, 48)),
+            (0, control_change(0, 101, 0)),
+            (0, control_change(0, 100, 0)),
+            (0, control_change(0, 6, 12)),
+            (0, control_change(0, 99, 1)),
+            (0, control_change(0, 98, 8)),
+            (0, control_change(0, 6, 64)),
+            (0, control_change(0, 38, 3)),
+            (240, bend),
+            (480, note_on(0, 60, 100)),
+            (960, note_off(0, 60)),
+            (960, end_of_track()),
+        ]]);
+
+        let state = smf.state_before(480);
+        let ch = state.channel(0);
+        assert_eq!(ch.program, Some(48));
+        assert_eq!(ch.bank(), (Some(1), None));
+        assert_eq!(ch.pitch_bend_range(), Some((12, 0)));
+        assert_eq!(ch.nrpn((1 << 7) | 8), Some((64 << 7) | 3));
+        assert_eq!(ch.pitch_bend, 10000);
+        assert!(ch.sounding.is_empty());
+        assert!(smf.state_at(480).channel(0).is_sounding(60));
+    }
+
+    #[test]
+    fn rpn_decode_encode_roundtrip() {
+        use super::types::rpn;
+        use super::types::state::Parameter;
+        use super::types::event::TrackChunk;
+
+        let mut track = TrackChunk::from_absolute(vec![(960, end_of_track())]);
+        track.insert_parameter(0, 0, Parameter::Rpn(0), rpn::join_14bit(12, 0), true);
+        track.insert_parameter(240, 1, Parameter::Nrpn(rpn::join_14bit(0x18, 36)), rpn::join_14bit(70, 0), false);
+        // increment the NRPN
+        track.insert_event(480, control_change(1, 96, 0));
+
+        let changes = rpn::decode_parameters(&track);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].pitch_bend_sensitivity(), Some((12, 0)));
+        assert_eq!((changes[0].tick, changes[0].index), (0, 3));
+        assert_eq!(changes[1].parameter, Parameter::Nrpn((0x18 << 7) | 36));
+        assert_eq!(changes[1].value, 70 << 7);
+        assert_eq!(changes[2].value, (70 << 7) + 1);
+        assert_eq!(track.len(), 12);
+
+        // Data entry MSB and LSB interleaved with another channel
+        let track = TrackChunk::from_absolute(vec![
+            (0, control_change(0, 101, 0)),
+            (0, control_change(0, 100, 0)),
+            (0, control_change(1, 101, 0)),
+            (0, control_change(1, 100, 0)),
+            (0, control_change(0, 6, 2)),
+            (0, control_change(1, 6, 12)),
+            (0, control_change(0, 38, 50)),
+            (0, control_change(1, 38, 0)),
+            (10, end_of_track()),
+        ]);
+        let changes = rpn::decode_parameters(&track);
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].channel, changes[0].pitch_bend_sensitivity(), changes[0].index), (0, Some((2, 50)), 6));
+        assert_eq!((changes[1].channel, changes[1].pitch_bend_sensitivity(), changes[1].index), (1, Some((12, 0)), 7));
+    }
+
+    #[test]
+    fn high_res_controller_view() {
+        use super::types::highres::HighResEvent;
+        use super::types::event::TrackChunk;
+
+        let track = TrackChunk::from_absolute(vec![
+            (0, control_change(0, 1, 64)),
+            (0, note_on(0, 60, 100)),
+            (0, control_change(0, 33, 5)),
+            (10, control_change(0, 11, 100)),
+            (20, control_change(0, 43, 1)),
+            (30, end_of_track()),
+        ]);
+        let view = track.to_high_res();
+        assert_eq!(view.len(), 5);
+        assert_eq!(view[0], (0, HighResEvent::Control{channel: 0, controller: 1, value: (64 << 7) | 5}));
+        assert_eq!(view[2], (10, HighResEvent::Event(control_change(0, 11, 100))));
+
+        let mut view = view;
+        view[0].1 = HighResEvent::Control{channel: 0, controller: 1, value: 0x3FFF};
+        let written = TrackChunk::from_high_res(view.clone()).unwrap();
+        assert_eq!(written.to_absolute()[..3], [
+            (0, control_change(0, 1, 127)),
+            (0, control_change(0, 33, 127)),
+            (0, note_on(0, 60, 100)),
+        ]);
+
+        view[0].1 = HighResEvent::Control{channel: 0, controller: 32, value: 0};
+        assert!(TrackChunk::from_high_res(view).is_err());
+        assert!(super::types::highres::control_change_14bit(0, 100, 0).is_none());
+    }
+
+    #[test]
+    fn gm_name_tables() {
+        use super::types::gm;
+        use super::types::message::ChannelVoiceMessage;
+
+        assert_eq!(gm::instrument_name(40), "Violin");
+        assert_eq!(gm::family_name(40), "Strings");
+        assert_eq!(gm::percussion_name(38), Some("Acoustic Snare"));
+        assert_eq!(gm::percussion_name(27), Some("High Q"));
+        assert_eq!(gm::percussion_name(100), None);
+        assert_eq!(gm::controller_description(39), "Channel Volume LSB");
+        assert_eq!(gm::gs_instrument_name(24, 8), Some("Ukulele"));
+        assert_eq!(gm::xg_drum_kit_name(25, 127), Some("Analog Kit"));
+
+        assert_eq!(ChannelVoiceMessage::ProgramChange{channel: 0, pc: 33}.describe(), "Ch.1 Program Change 33 (Electric Bass (finger))");
+        assert_eq!(ChannelVoiceMessage::ProgramChange{channel: 9, pc: 25}.program_name(), Some("Analog Set"));
+        assert_eq!(ChannelVoiceMessage::ProgramChange{channel: 9, pc: 25}.gs_program_name(), Some("TR-808"));
+        assert_eq!(ChannelVoiceMessage::ProgramChange{channel: 9, pc: 127}.program_name(), None);
+        assert_eq!(ChannelVoiceMessage::NoteOn{channel: 9, key: 42, vel: 90}.describe(), "Ch.10 Note On Closed Hi Hat vel 90");
+        assert_eq!(ChannelVoiceMessage::NoteOn{channel: 0, key: 60, vel: 90}.describe(), "Ch.1 Note On C4 vel 90");
+        assert_eq!(ChannelVoiceMessage::ControlChange{channel: 0, cc: 7, value: 100}.controller_name(), Some("Channel Volume"));
+        assert_eq!(ChannelVoiceMessage::ControlChange{channel: 0, cc: 3, value: 0}.controller_name(), gm::controller_name(3));
+        assert_eq!(gm::gm2_instrument_name(125, 5), Some("Siren"));
+        for (i, (program, lsb, _)) in gm::GM2_VARIATIONS.iter().enumerate() {
+            assert!(*lsb > 0);
+            assert!(gm::GM2_VARIATIONS[i + 1..].iter().all(|(p, l, _)| (p, l) != (program, lsb)), "{} {} appears twice", program, lsb);
+        }
+        assert_eq!(gm::gs_instrument_name(127, 3), Some("Explosion"));
+        assert_eq!(gm::gs_instrument_name(0, 127), Some("Acou Piano 1"));
+        assert_eq!(gm::xg_instrument_name(5, 0, 34), Some("DXLegend"));
+        assert_eq!(gm::xg_instrument_name(84, 64, 0), Some("Siren"));
+        assert_eq!(gm::xg_instrument_name(0, 127, 0), Some("Standard Kit"));
+    }
+
+    #[test]
+    fn sysex_decode_and_build() {
+        use super::types::sysex::SysExMessage;
+        use super::types::message::SysExEvent;
+
+        let gs = SysExMessage::gs_reset().to_event();
+        assert_eq!(gs.raw(), vec![0xF0, 0x0A, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41, 0xF7]);
+        assert_eq!(SysExMessage::decode(&gs).unwrap(), SysExMessage::GsReset{device_id: 0x10});
+
+        let broken = SysExEvent::SysExF0{length: 10, data: vec![0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x40, 0xF7]};
+        assert!(SysExMessage::decode(&broken).is_err());
+
+        let xg = SysExMessage::xg_system_on().to_event();
+        assert_eq!(xg.raw(), vec![0xF0, 0x08, 0x43, 0x10, 0x4C, 0x00, 0x00, 0x7E, 0x00, 0xF7]);
+        assert_eq!(SysExMessage::decode(&xg).unwrap(), SysExMessage::xg_system_on());
+
+        let volume = SysExEvent::SysExF0{length: 7, data: vec![0x7F, 0x7F, 0x04, 0x01, 0x00, 0x40, 0xF7]};
+        assert_eq!(SysExMessage::decode(&volume).unwrap(), SysExMessage::master_volume(0x40 << 7));
+        assert_eq!(SysExMessage::decode(&SysExMessage::gm_system_on().to_event()).unwrap(), SysExMessage::gm_system_on());
+    }
+
+    #[test]
+    fn sysex_packets_reassembly() {
+        use super::types::sysex::{SysExItem, SysExMessage};
+        use super::types::message::SysExEvent;
+        use super::types::event::{MidiEvent, TrackChunk};
+
+        let gs = SysExMessage::gs_reset().payload();
+        let mut track = TrackChunk::from_absolute(vec![
+            (0, MidiEvent::SysExEvent(SysExMessage::gs_reset().to_event())),
+            (10, MidiEvent::SysExEvent(SysExEvent::SysExF7{length: 2, data: vec![0xF3, 0x01]})),
+            (20, end_of_track()),
+        ]);
+        track.resplit_sysex(4, 5);
+        assert_eq!(track.len(), 5);
+
+        let items = track.sysex_items();
+        assert_eq!(items.len(), 2);
+        match &items[0] {
+            SysExItem::Message(message) => {
+                assert!(message.complete);
+                assert_eq!(message.packets.iter().map(|p| p.tick).collect::<Vec<_>>(), vec![0, 5, 10]);
+                assert_eq!(message.payload, gs);
+                assert_eq!(message.decode().unwrap(), SysExMessage::gs_reset());
+            },
+            _ => panic!("expected SysEx message"),
+        }
+        assert_eq!(items[1], SysExItem::Escape{tick: 10, index: 3, data: vec![0xF3, 0x01]});
+
+        track.join_sysex();
+        assert_eq!(track[0].event(), &MidiEvent::SysExEvent(SysExMessage::gs_reset().to_event()));
+        assert_eq!(track.len(), 3);
+
+        // A SysEx on the last tick: EndOfTrack follows its last packet
+        let mut track = TrackChunk::from_absolute(vec![
+            (20, MidiEvent::SysExEvent(SysExMessage::gs_reset().to_event())),
+            (20, end_of_track()),
+        ]);
+        track.resplit_sysex(4, 5);
+        assert_eq!(track.absolute_ticks(), vec![20, 25, 30, 30]);
+        assert!(track[3].event().is_end_of_track());
+    }
+
+    #[test]
+    fn roland_gs_parameters() {
+        use super::types::roland::{GsParameter, RolandSysEx, gs_part_block};
+        use super::types::sysex::SysExMessage;
+        use super::types::message::SysExEvent;
+
+        assert_eq!(gs_part_block(9), 0);
+        assert_eq!(gs_part_block(0), 1);
+        assert_eq!(gs_part_block(10), 0x0A);
+
+        // Use channel 11 as drum part with map 1
+        let message = GsParameter::PartUseForRhythm(10).set(1);
+        let event = message.to_event();
+        assert_eq!(event.raw(), vec![0xF0, 0x0A, 0x41, 0x10, 0x42, 0x12, 0x40, 0x1A, 0x15, 0x01, 0x10, 0xF7]);
+        assert_eq!(RolandSysEx::parse(&event).unwrap().gs_parameter(), Some(GsParameter::PartUseForRhythm(10)));
+        assert_eq!(SysExMessage::decode(&event).unwrap(), SysExMessage::Roland(message));
+
+        let reverb = GsParameter::ReverbMacro.set(4).to_event();
+        assert_eq!(reverb.raw()[6..12], [0x40, 0x01, 0x30, 0x04, 0x0B, 0xF7]);
+
+        let broken = SysExEvent::SysExF0{length: 10, data: vec![0x41, 0x10, 0x42, 0x12, 0x40, 0x1A, 0x15, 0x01, 0x11, 0xF7]};
+        assert!(RolandSysEx::parse(&broken).is_err());
+        assert_eq!(SysExMessage::decode(&broken).unwrap(), SysExMessage::Other(broken.raw()[2..11].to_vec()));
+    }
+
+    #[test]
+    fn microtonal_tuning() {
+        use super::file::scala::{KeyboardMapping, ScalaScale};
+        use super::types::tuning::{NoteTuning, TuningMessage};
+        use super::types::message::{ChannelVoiceMessage, MidiChannelMessage};
+        use super::types::event::MidiEvent;
+        use super::ops::microtonal::{MtsFormat, PitchBendTuningOptions};
+
+        let scale = ScalaScale::parse("! just.scl\n!\nJust major\n 7\n!\n9/8\n5/4\n4/3\n3/2\n5/3\n15/8\n2\n").unwrap();
+        assert_eq!(scale.description, "Just major");
+        assert!((scale.pitches[1] - 386.3137).abs() < 1e-3);
+        let mapping = KeyboardMapping::parse("! white keys\n12\n0\n127\n60\n69\n440.0\n7\n0\nx\n1\nx\n2\n3\nx\n4\nx\n5\nx\n6\n").unwrap();
+        let tuning = scale.tuning(&mapping);
+        assert!((tuning.frequency(69).unwrap() - 440.0).abs() < 1e-9);
+        assert!((tuning.frequency(60).unwrap() - 264.0).abs() < 1e-9);
+        assert!((tuning.frequency(76).unwrap() - 660.0).abs() < 1e-9);
+        assert_eq!(tuning.frequency(61), None);
+
+        let dump = TuningMessage::bulk_dump(0x7F, 1, "just", &tuning);
+        let event = dump.to_event();
+        assert_eq!(event.raw().len(), 1 + 2 + 4 + 1 + 16 + 384 + 1 + 1);
+        assert_eq!(TuningMessage::decode(&event).unwrap(), dump);
+        let change = TuningMessage::SingleNoteTuningChange{device_id: 0x7F, program: 0, changes: vec![(69, NoteTuning::from_key(69.5))]};
+        assert_eq!(&change.to_event().raw()[2..], &[0x7F, 0x7F, 0x08, 0x02, 0x00, 0x01, 69, 69, 0x40, 0x00, 0xF7]);
+
+        let smf = smf_of(480, vec![vec![
+            (0, program_change(0, 19)),
+            (0, note_on(0, 60, 100)),
+            (0, note_on(0, 64, 100)),
+            (480, note_off(0, 60)),
+            (480, note_off(0, 64)),
+            (480, end_of_track()),
+        ]]);
+
+        let mut mts = smf.clone();
+        mts.retune_mts(&tuning, 1, "just", MtsFormat::BulkDump);
+        assert!(matches!(mts.tracks()[0][0].event(), MidiEvent::SysExEvent(_)));
+        assert_eq!(mts.tracks()[0][1].event(), &control_change(0, 101, 0));
+        assert_eq!(mts.tracks()[0][2].event(), &control_change(0, 100, 3));
+        assert_eq!(mts.tracks()[0][3].event(), &control_change(0, 6, 1));
+
+        let mut bent = smf.clone();
+        let opts = PitchBendTuningOptions {channels: vec![0, 1], bend_range: 2};
+        bent.retune_pitch_bend(&tuning, &opts).unwrap();
+        let events: Vec<(u32, MidiEvent)> = bent.tracks()[0].to_absolute().into_iter().skip(12).collect();
+        let bend = |channel: u8, value: u16| MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::pitch_bend(channel, value)));
+        // With A at 440 Hz, the just C is 15.6 cents sharp and E is 2 cents sharp
+        assert_eq!(events, vec![
+            (0, program_change(0, 19)),
+            (0, program_change(1, 19)),
+            (0, bend(0, 8833)),
+            (0, bend(1, 8272)),
+            (0, note_on(0, 60, 100)),
+            (0, note_on(1, 64, 100)),
+            (480, note_off(0, 60)),
+            (480, note_off(1, 64)),
+            (480, end_of_track()),
+        ]);
+
+        // A single channel pool: the first note is ended when the second needs the channel
+        let pressure = |channel: u8, key: u8, vel: u8| MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::PolyphonicKeyPressure{channel, key, vel}));
+        let mut stolen = smf_of(480, vec![vec![
+            (0, note_on(2, 60, 100)),
+            (240, note_on(2, 64, 100)),
+            (300, pressure(2, 64, 50)),
+            (300, pressure(2, 60, 50)),
+            (480, note_off(2, 60)),
+            (480, note_off(2, 64)),
+            (480, end_of_track()),
+        ]]);
+        let opts = PitchBendTuningOptions {channels: vec![2], bend_range: 2};
+        stolen.retune_pitch_bend(&tuning, &opts).unwrap();
+        let events: Vec<(u32, MidiEvent)> = stolen.tracks()[0].to_absolute().into_iter().skip(6).collect();
+        assert_eq!(events, vec![
+            (0, bend(2, 8833)),
+            (0, note_on(2, 60, 100)),
+            (240, note_off(2, 60)),
+            (240, bend(2, 8272)),
+            (240, note_on(2, 64, 100)),
+            (300, pressure(2, 64, 50)),
+            (480, note_off(2, 64)),
+            (480, end_of_track()),
+        ]);
+    }
+
+    #[test]
+    fn sequencer_playback() {
+        use super::playback::clock::ManualClock;
+        use super::playback::output::{OutputMessage, RecordingOutput};
+        use super::playback::sequencer::{PlaybackStatus, Sequencer};
+        use super::types::event::MidiEvent;
+        use super::types::message::{ChannelModeMessage, MidiChannelMessage};
+
+        let channel = |event: MidiEvent| match event {
+            MidiEvent::MidiChannelMessage(message) => OutputMessage::Channel(message),
+            _ => unreachable!(),
+        };
+        let mode = |message: ChannelModeMessage| OutputMessage::Channel(MidiChannelMessage::ChannelModeMessage(message));
+
+        // 120 BPM: a quarter note is 0.5 seconds
+        let smf = smf_of(480, vec![vec![
+            (0, control_change(0, 7, 100)),
+            (0, note_on(0, 60, 100)),
+            (480, note_off(0, 60)),
+            (480, note_on(0, 62, 100)),
+            (960, note_off(0, 62)),
+            (960, end_of_track()),
+        ]]);
+        let clock = ManualClock::new();
+        let mut seq = Sequencer::with_clock(&smf, RecordingOutput::new(), clock.clone());
+        assert_eq!(seq.duration(), 1.0);
+
+        clock.set(10.0);
+        seq.play();
+        seq.update();
+        assert_eq!(seq.output_mut().take(), vec![channel(control_change(0, 7, 100)), channel(note_on(0, 60, 100))]);
+        assert_eq!(seq.next_event_time(), Some(10.5));
+        clock.set(10.4);
+        seq.update();
+        assert!(seq.output().messages.is_empty());
+        clock.set(10.5);
+        seq.update();
+        assert_eq!(seq.output_mut().take(), vec![channel(note_off(0, 60)), channel(note_on(0, 62, 100))]);
+
+        // Pausing ends the sounding note, stopping sends all-notes-off
+        clock.set(10.6);
+        seq.pause();
+        assert!((seq.position() - 0.6).abs() < 1e-9);
+        assert_eq!(seq.output_mut().take(), vec![channel(note_off(0, 62))]);
+        seq.stop();
+        assert_eq!(seq.output_mut().take(), vec![mode(ChannelModeMessage::AllNotesOff{channel: 0})]);
+        assert_eq!(seq.status(), PlaybackStatus::Stopped);
+
+        // Seeking restores the volume set before the position
+        clock.set(20.0);
+        seq.seek_tick(480);
+        assert_eq!(seq.output_mut().take(), vec![mode(ChannelModeMessage::ResetAllControllers{channel: 0}), channel(control_change(0, 7, 100))]);
+        seq.set_speed(2.0);
+        seq.play();
+        seq.update();
+        assert_eq!(seq.output_mut().take(), vec![channel(note_on(0, 62, 100))]);
+        assert_eq!(seq.next_event_time(), Some(20.25));
+        clock.set(20.25);
+        seq.update();
+        assert_eq!(seq.output_mut().take(), vec![channel(note_off(0, 62)), mode(ChannelModeMessage::AllNotesOff{channel: 0})]);
+        assert_eq!(seq.status(), PlaybackStatus::Stopped);
+
+        // Looping the first beat
+        clock.set(30.0);
+        seq.set_speed(1.0);
+        seq.set_loop(Some((0, 480)));
+        seq.play();
+        seq.update();
+        seq.output_mut().take();
+        clock.set(30.6);
+        seq.update();
+        // The volume is unset before tick 0, so its default is sent before the volume of tick 0
+        assert_eq!(seq.output_mut().take(), vec![
+            channel(note_off(0, 60)),
+            mode(ChannelModeMessage::ResetAllControllers{channel: 0}),
+            channel(control_change(0, 7, 100)),
+            channel(control_change(0, 7, 100)),
+            channel(note_on(0, 60, 100)),
+        ]);
+        assert!((seq.position() - 0.1).abs() < 1e-9);
+        assert_eq!(seq.status(), PlaybackStatus::Playing);
+
+        // Seeking back before a bank, program and volume change returns them to their defaults
+        let smf = smf_of(480, vec![vec![
+            (0, note_on(0, 60, 100)),
+            (240, note_off(0, 60)),
+            (480, control_change(0, 0, 1)),
+            (480, program_change(0, 5)),
+            (480, control_change(0, 7, 80)),
+            (960, end_of_track()),
+        ]]);
+        let mut seq = Sequencer::with_clock(&smf, RecordingOutput::new(), clock.clone());
+        seq.seek_tick(960);
+        assert_eq!(seq.output_mut().take(), vec![
+            mode(ChannelModeMessage::ResetAllControllers{channel: 0}),
+            channel(control_change(0, 0, 1)),
+            channel(program_change(0, 5)),
+            channel(control_change(0, 7, 80)),
+        ]);
+        seq.seek_tick(0);
+        assert_eq!(seq.output_mut().take(), vec![
+            mode(ChannelModeMessage::ResetAllControllers{channel: 0}),
+            channel(control_change(0, 0, 0)),
+            channel(program_change(0, 0)),
+            channel(control_change(0, 7, 100)),
+        ]);
+    }
+
+    #[test]
+    fn sequencer_midi_clock() {
+        use super::playback::clock::{Clock, ManualClock};
+        use super::playback::output::{OutputMessage, RecordingOutput};
+        use super::playback::sequencer::{PlaybackStatus, Sequencer};
+        use super::types::message::{SystemCommonMessage, SystemRealTimeMessage};
+
+        // One beat at 120 BPM, then one beat at 60 BPM
+        let smf = smf_of(96, vec![vec![
+            (0, note_on(0, 60, 100)),
+            (96, set_tempo(1_000_000)),
+            (192, note_off(0, 60)),
+            (192, end_of_track()),
+        ]]);
+        let clock = ManualClock::new();
+        let mut seq = Sequencer::with_clock(&smf, RecordingOutput::new(), clock.clone());
+        seq.set_send_clock(true);
+        seq.run();
+        assert_eq!(seq.status(), PlaybackStatus::Stopped);
+        assert_eq!(clock.now(), 1.5);
+
+        let messages = seq.output_mut().take();
+        let pulses = messages.iter().filter(|m| **m == OutputMessage::RealTime(SystemRealTimeMessage::TimingClock)).count();
+        assert_eq!(pulses, 49);
+        assert_eq!(messages[0], OutputMessage::RealTime(SystemRealTimeMessage::Start));
+        assert_eq!(messages[messages.len() - 2], OutputMessage::RealTime(SystemRealTimeMessage::Stop));
+
+        // Pulses follow the tempo: 24 in the first half second, 12 in the next half second
+        seq.play();
+        seq.update();
+        seq.output_mut().take();
+        clock.advance(0.5);
+        seq.update();
+        assert_eq!(seq.output_mut().take().len(), 24);
+        clock.advance(0.5);
+        seq.update();
+        assert_eq!(seq.output_mut().take().len(), 12);
+
+        // Resuming after a seek gives the position in sixteenths, rounded up to the next pulse on one
+        let song_position = |beats: u16| OutputMessage::Common(SystemCommonMessage::SongPosition{beats});
+        seq.stop();
+        seq.seek_tick(100);
+        seq.output_mut().take();
+        seq.play();
+        assert_eq!(seq.output_mut().take(), vec![song_position(5), OutputMessage::RealTime(SystemRealTimeMessage::Continue)]);
+
+        // Seeking while playing stops the receiver and moves it
+        seq.seek_tick(48);
+        let messages = seq.output_mut().take();
+        assert_eq!(messages[messages.len() - 3..], [
+            OutputMessage::RealTime(SystemRealTimeMessage::Stop),
+            song_position(2),
+            OutputMessage::RealTime(SystemRealTimeMessage::Continue),
+        ]);
+    }
+
+    #[test]
+    fn wire_stream_parsing() {
+        use super::wire::parser::{WireMessage, WireParser};
+        use super::types::event::MidiEvent;
+        use super::types::message::{SysExEvent, SystemCommonMessage, SystemRealTimeMessage};
+
+        let channel = |event: MidiEvent| match event {
+            MidiEvent::MidiChannelMessage(message) => WireMessage::Channel(message),
+            _ => unreachable!(),
+        };
+        let clock = WireMessage::RealTime(SystemRealTimeMessage::TimingClock);
+
+        let mut parser = WireParser::new();
+        // Running status with a clock byte in the middle of a message
+        assert_eq!(parser.parse(&[0x90, 0x3C]), vec![]);
+        assert_eq!(parser.parse(&[0x64, 0xF8, 0x40]), vec![channel(note_on(0, 60, 100)), clock.clone()]);
+        assert_eq!(parser.parse(&[0x00, 0xFA]), vec![channel(note_on(0, 64, 0)), WireMessage::RealTime(SystemRealTimeMessage::Start)]);
+
+        // SysEx with a real-time byte inside
+        let messages = parser.parse(&[0xF0, 0x7E, 0x7F, 0xF8, 0x09, 0x01, 0xF7]);
+        let gm_on = SysExEvent::SysExF0{length: 5, data: vec![0x7E, 0x7F, 0x09, 0x01, 0xF7]};
+        assert_eq!(messages, vec![clock.clone(), WireMessage::SysEx(gm_on.clone())]);
+        assert_eq!(messages[1].to_midi_event(), Some(MidiEvent::SysExEvent(gm_on)));
+
+        // System Common messages cancel running status, so the stray 0x3C is ignored
+        assert_eq!(parser.parse(&[0xF2, 0x10, 0x02, 0x3C, 0xF1, 0x25, 0xF3, 0x05, 0xF6]), vec![
+            WireMessage::SystemCommon(SystemCommonMessage::SongPosition{beats: 272}),
+            WireMessage::SystemCommon(SystemCommonMessage::MtcQuarterFrame{message_type: 2, value: 5}),
+            WireMessage::SystemCommon(SystemCommonMessage::SongSelect{song: 5}),
+            WireMessage::SystemCommon(SystemCommonMessage::TuneRequest),
+        ]);
+
+        // An interrupted SysEx is discarded
+        assert_eq!(parser.parse(&[0xC1, 0x05, 0x06, 0xF0, 0x43, 0xB0, 0x7B, 0x00]), vec![
+            channel(program_change(1, 5)),
+            channel(program_change(1, 6)),
+            channel(control_change(0, 0x7B, 0)),
+        ]);
+
+        // A stray F7 also cancels running status
+        assert_eq!(parser.parse(&[0xF7, 0x7B, 0x00]), vec![]);
+        assert_eq!(parser.parse(&[0xB0, 0x7B, 0x00]), vec![channel(control_change(0, 0x7B, 0))]);
+    }
 }
@@ -0,0 +1,106 @@
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+use crate::types::message::MetaEvent;
+
+/// Channel remapping table. Channels are 0-15.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMap {
+    table: [u8; 16],
+}
+
+impl ChannelMap {
+    /// Maps every channel to itself
+    pub fn identity() -> ChannelMap {
+        let mut table = [0; 16];
+        for (i, ch) in table.iter_mut().enumerate() {
+            *ch = i as u8;
+        }
+        ChannelMap {table}
+    }
+
+    /// Creates a map from the target channel of every channel. Entries are masked to 0-15 like set.
+    pub fn from_table(table: [u8; 16]) -> ChannelMap {
+        let mut table = table;
+        for ch in table.iter_mut() {
+            *ch &= 0x0F;
+        }
+        ChannelMap {table}
+    }
+
+    /// Maps channel from to channel to
+    pub fn set(&mut self, from: u8, to: u8) -> &mut ChannelMap {
+        self.table[(from & 0x0F) as usize] = to & 0x0F;
+        self
+    }
+
+    pub fn get(&self, channel: u8) -> u8 {
+        self.table[(channel & 0x0F) as usize]
+    }
+}
+
+impl Default for ChannelMap {
+    fn default() -> ChannelMap {
+        ChannelMap::identity()
+    }
+}
+
+/// Returns the channel of a channel message or MIDIChannelPrefix meta event
+fn event_channel(event: &MidiEvent) -> Option<u8> {
+    match event {
+        MidiEvent::MidiChannelMessage(message) => Some(message.channel()),
+        MidiEvent::MetaEvent(MetaEvent::MIDIChannelPrefix{channel}) => Some(*channel),
+        _ => None
+    }
+}
+
+impl TrackChunk {
+    /// Rewrites the channel of every channel message and MIDIChannelPrefix meta event
+    pub fn remap_channels(&mut self, map: &ChannelMap) {
+        for pair in &mut self.events {
+            match pair.event_mut() {
+                MidiEvent::MidiChannelMessage(message) => {
+                    let channel = map.get(message.channel());
+                    message.set_channel(channel);
+                },
+                MidiEvent::MetaEvent(MetaEvent::MIDIChannelPrefix{channel}) => *channel = map.get(*channel),
+                _ => {}
+            }
+        }
+    }
+
+    /// Removes channel messages and MIDIChannelPrefix meta events of channels for which the predicate returns false
+    pub fn filter_channels<F>(&mut self, mut f: F) where F: FnMut(u8) -> bool {
+        self.retain_events(|event| event_channel(event).is_none_or(&mut f));
+    }
+
+    /// Keeps only the given channels
+    pub fn keep_channels(&mut self, channels: &[u8]) {
+        self.filter_channels(|ch| channels.contains(&ch));
+    }
+
+    /// Removes the given channels
+    pub fn drop_channels(&mut self, channels: &[u8]) {
+        self.filter_channels(|ch| !channels.contains(&ch));
+    }
+}
+
+impl SMF {
+    pub fn remap_channels(&mut self, map: &ChannelMap) {
+        for track in self.tracks_mut() {
+            track.remap_channels(map);
+        }
+    }
+
+    pub fn filter_channels<F>(&mut self, mut f: F) where F: FnMut(u8) -> bool {
+        for track in self.tracks_mut() {
+            track.filter_channels(&mut f);
+        }
+    }
+
+    pub fn keep_channels(&mut self, channels: &[u8]) {
+        self.filter_channels(|ch| channels.contains(&ch));
+    }
+
+    pub fn drop_channels(&mut self, channels: &[u8]) {
+        self.filter_channels(|ch| !channels.contains(&ch));
+    }
+}
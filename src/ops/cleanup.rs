@@ -0,0 +1,135 @@
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+
+use super::notes::{note_edge, NoteEdge};
+
+/// Number of problems fixed by cleanup_notes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CleanupReport {
+    /// Notes cut at the start of the next note of the same key
+    pub overlapping: usize,
+    /// Notes removed because they ended on the tick they started
+    pub zero_length: usize,
+    /// NoteOns removed because the same key was already started on the same tick
+    pub duplicates: usize,
+    /// NoteOffs removed because no note of the key was sounding
+    pub orphan_note_offs: usize,
+    /// NoteOffs added at the track end for notes that never ended
+    pub unterminated: usize,
+}
+
+impl CleanupReport {
+    pub fn total(&self) -> usize {
+        self.overlapping + self.zero_length + self.duplicates + self.orphan_note_offs + self.unterminated
+    }
+
+    fn add(&mut self, other: &CleanupReport) {
+        self.overlapping += other.overlapping;
+        self.zero_length += other.zero_length;
+        self.duplicates += other.duplicates;
+        self.orphan_note_offs += other.orphan_note_offs;
+        self.unterminated += other.unterminated;
+    }
+}
+
+impl TrackChunk {
+    /// Repairs overlapping, zero-length, duplicated, orphaned and unterminated notes
+    pub fn cleanup_notes(&mut self) -> CleanupReport {
+        let mut report = CleanupReport::default();
+        let mut events: std::vec::Vec<Option<(u32, MidiEvent)>> = self.to_absolute().into_iter().map(Some).collect();
+        let end_tick = self.end_tick();
+        let mut notes = self.notes();
+
+        // Orphan NoteOffs
+        let paired: std::collections::HashSet<usize> = notes.iter().filter_map(|note| note.off_index).collect();
+        for (i, pair) in self.events.iter().enumerate() {
+            if let Some(NoteEdge::Off{..}) = note_edge(pair.event()) {
+                if !paired.contains(&i) {
+                    events[i] = None;
+                    report.orphan_note_offs += 1;
+                }
+            }
+        }
+
+        // Notes of the same key in start order
+        let mut by_key: std::collections::BTreeMap<(u8, u8), std::vec::Vec<usize>> = std::collections::BTreeMap::new();
+        for (i, note) in notes.iter().enumerate() {
+            by_key.entry((note.channel, note.key)).or_default().push(i);
+        }
+
+        let mut removed = vec![false; notes.len()];
+        for indices in by_key.values() {
+            let mut current: Option<usize> = None;
+            for &i in indices {
+                if let Some(c) = current {
+                    if notes[c].start == notes[i].start {
+                        // Duplicate: keep one note lasting as long as the longer of the two
+                        report.duplicates += 1;
+                        removed[i] = true;
+                        events[notes[i].on_index] = None;
+                        if let Some(off) = notes[i].off_index {
+                            if let Some(c_off) = notes[c].off_index {
+                                events[c_off] = None;
+                            }
+                            notes[c].end = notes[i].end;
+                            notes[c].off_index = Some(off);
+                        }
+                        continue;
+                    }
+
+                    if notes[c].end.is_none_or(|end| end > notes[i].start) {
+                        report.overlapping += 1;
+                        notes[c].end = Some(notes[i].start);
+                    }
+                }
+                current = Some(i);
+            }
+        }
+
+        let mut added: std::vec::Vec<(u32, MidiEvent)> = std::vec::Vec::new();
+        for (i, note) in notes.iter().enumerate() {
+            if removed[i] {
+                continue;
+            }
+            match (note.end, note.off_index) {
+                (Some(end), _) if end == note.start => {
+                    report.zero_length += 1;
+                    events[note.on_index] = None;
+                    if let Some(off) = note.off_index {
+                        events[off] = None;
+                    }
+                },
+                (Some(end), Some(off)) => {
+                    if let Some(event) = events[off].as_mut() {
+                        event.0 = end;
+                    }
+                },
+                (Some(end), None) => {
+                    // Cut by an overlapping note without having a NoteOff
+                    added.push((end, super::note_off(note.channel, note.key)));
+                },
+                (None, _) => {
+                    report.unterminated += 1;
+                    added.push((end_tick.max(note.start + 1), super::note_off(note.channel, note.key)));
+                },
+            }
+        }
+
+        if report.total() > 0 {
+            let mut events: std::vec::Vec<(u32, MidiEvent)> = events.into_iter().flatten().collect();
+            events.extend(added);
+            *self = super::rebuild_track(events);
+        }
+        report
+    }
+}
+
+impl SMF {
+    /// Repairs notes in every track and returns the sum of the reports
+    pub fn cleanup_notes(&mut self) -> CleanupReport {
+        let mut report = CleanupReport::default();
+        for track in self.tracks_mut() {
+            report.add(&track.cleanup_notes());
+        }
+        report
+    }
+}
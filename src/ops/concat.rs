@@ -0,0 +1,88 @@
+use crate::file::filerw::SmfError;
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+use crate::types::message::MetaEvent;
+use crate::types::tempo::DEFAULT_TEMPO;
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// How tracks of the appended file are matched with the existing tracks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackMatching {
+    /// The n-th track is appended to the n-th track
+    ByIndex,
+    /// Tracks are appended to the track with the same SequenceTrackName
+    ByName,
+}
+
+impl SMF {
+    /// Returns the tick of the last EndOfTrack among all tracks
+    pub fn end_tick(&self) -> u32 {
+        self.tracks().iter().map(|track| track.end_tick()).max().unwrap_or(0)
+    }
+
+    /// Appends another file after the end of this one.
+    /// The other file is rescaled to this resolution if they differ, and EndOfTrack events
+    /// in the middle of joined tracks are removed. Tracks without a match are added as new tracks.
+    /// If the other file sets no tempo at tick 0, its initial tempo (120 BPM by default) is set where it starts.
+    /// Fails if the joined file would be longer than u32::MAX ticks.
+    pub fn append(&mut self, other: &SMF, matching: TrackMatching) -> Result<()> {
+        let mut other = other.clone();
+        if other.resolution() != self.resolution() {
+            other.rescale_resolution(self.resolution())?;
+        }
+
+        let offset = self.end_tick();
+        if offset.checked_add(other.end_tick()).is_none() {
+            return Err(SmfError::new("appended file ends past the last representable tick"));
+        }
+        let has_initial_tempo = other.tracks().iter().any(|track| track.to_absolute().iter()
+            .take_while(|(tick, _)| *tick == 0)
+            .any(|(_, event)| matches!(event, MidiEvent::MetaEvent(MetaEvent::SetTempo{..}))));
+        let mut used = vec![false; self.tracks().len()];
+        for (i, track) in other.into_tracks().into_iter().enumerate() {
+            let target = match matching {
+                TrackMatching::ByIndex => if i < used.len() { Some(i) } else { None },
+                TrackMatching::ByName => track.name().and_then(|name| {
+                    self.tracks().iter().enumerate()
+                        .position(|(j, t)| !used[j] && t.name().as_ref() == Some(&name))
+                }),
+            };
+
+            match target {
+                Some(j) => {
+                    used[j] = true;
+                    let joined = self.tracks()[j].to_absolute().into_iter()
+                        .filter(|(_, event)| !event.is_end_of_track())
+                        .chain(track.to_absolute().into_iter()
+                            .filter(|(_, event)| !matches!(event, MidiEvent::MetaEvent(MetaEvent::SequenceTrackName{..})))
+                            .map(|(tick, event)| (tick + offset, event)))
+                        .collect();
+                    self.tracks_mut()[j] = TrackChunk::from_absolute(joined);
+                },
+                None => {
+                    let shifted = track.to_absolute().into_iter().map(|(tick, event)| (tick + offset, event)).collect();
+                    self.add_track(TrackChunk::from_absolute(shifted))?;
+                }
+            }
+        }
+
+        if !has_initial_tempo && !self.tracks().is_empty() {
+            self.tracks_mut()[0].insert_event(offset, MidiEvent::MetaEvent(MetaEvent::SetTempo{tempo: DEFAULT_TEMPO}));
+        }
+
+        if self.tracks().len() > 1 && self.format() == 0 {
+            self.set_format(1);
+        }
+        Ok(())
+    }
+
+    /// Joins files end to end. The resolution of the first file is used.
+    pub fn concat(files: &[SMF], matching: TrackMatching) -> Result<SMF> {
+        let (first, rest) = files.split_first().ok_or_else(|| SmfError::new("no files to concatenate"))?;
+        let mut joined = first.clone();
+        for smf in rest {
+            joined.append(smf, matching)?;
+        }
+        Ok(joined)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+use crate::types::message::{ChannelVoiceMessage, MidiChannelMessage};
+
+use super::quantize::Grid;
+
+/// SplitMix64 generator. Small and reproducible across platforms, which is all humanize needs.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng {state: seed}
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in [-max, max]
+    fn offset(&mut self, max: u32) -> i64 {
+        if max == 0 {
+            return 0;
+        }
+        let span = 2 * max as u64 + 1;
+        (self.next_u64() % span) as i64 - max as i64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HumanizeOptions {
+    /// The same seed always produces the same result
+    pub seed: u64,
+    /// Maximum start time shift in ticks
+    pub timing: u32,
+    /// Maximum duration change in ticks
+    pub duration: u32,
+    /// Maximum velocity change
+    pub velocity: u8,
+    /// Notes are not moved more than half a step away from their nearest grid point
+    pub grid: Option<Grid>,
+}
+
+impl HumanizeOptions {
+    pub fn new(seed: u64) -> HumanizeOptions {
+        HumanizeOptions {seed, timing: 0, duration: 0, velocity: 0, grid: None}
+    }
+}
+
+impl TrackChunk {
+    /// Randomly perturbs note starts, durations and velocities.
+    /// Times never become negative, notes never end before they start and
+    /// consecutive notes of the same key never overlap if they did not before.
+    pub fn humanize(&mut self, resolution: u16, options: &HumanizeOptions) {
+        let mut rng = Rng::new(options.seed);
+        let mut events = self.to_absolute();
+        let notes = self.notes();
+
+        // (start, end) after perturbation
+        let mut timings: std::vec::Vec<(u32, Option<u32>)> = std::vec::Vec::with_capacity(notes.len());
+        for note in &notes {
+            let mut start = (note.start as i64 + rng.offset(options.timing)).max(0) as u32;
+            if let Some(grid) = &options.grid {
+                let point = grid.nearest(resolution, note.start) as f64;
+                let half = grid.step(resolution) / 2.0;
+                let low = (point - half).max(0.0);
+                start = (start as f64).clamp(low, (point + half - 1.0).max(low)).round() as u32;
+            }
+            let end = note.duration().map(|duration| {
+                start + (duration as i64 + rng.offset(options.duration)).max(1) as u32
+            });
+            timings.push((start, end));
+
+            let velocity_offset = rng.offset(options.velocity as u32);
+            if let MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOn{vel, ..})) = &mut events[note.on_index].1 {
+                *vel = (*vel as i64 + velocity_offset).clamp(1, 127) as u8;
+            }
+        }
+
+        // Keep notes of the same key in order and not overlapping
+        let mut previous: std::collections::HashMap<(u8, u8), usize> = std::collections::HashMap::new();
+        for (i, note) in notes.iter().enumerate() {
+            if let Some(&p) = previous.get(&(note.channel, note.key)) {
+                if notes[p].end.is_some_and(|end| end <= note.start) {
+                    let (prev_start, prev_end) = timings[p];
+                    let start = timings[i].0.max(prev_start + 1);
+                    timings[p].1 = prev_end.map(|end| end.min(start));
+                    timings[i].0 = start;
+                    timings[i].1 = timings[i].1.map(|end| end.max(start + 1));
+                }
+            }
+            previous.insert((note.channel, note.key), i);
+        }
+
+        for (note, (start, end)) in notes.iter().zip(timings) {
+            events[note.on_index].0 = start;
+            if let (Some(off), Some(end)) = (note.off_index, end) {
+                events[off].0 = end;
+            }
+        }
+
+        *self = super::rebuild_track(events);
+    }
+}
+
+impl SMF {
+    /// Humanizes every track. Each track uses a different seed derived from HumanizeOptions::seed.
+    pub fn humanize(&mut self, options: &HumanizeOptions) {
+        let resolution = self.resolution();
+        for (i, track) in self.tracks_mut().iter_mut().enumerate() {
+            let mut options = options.clone();
+            options.seed = options.seed.wrapping_add(i as u64);
+            track.humanize(resolution, &options);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use super::notes::Note;
+use crate::file::filerw::SmfError;
+use crate::types::event::{MidiEvent, SMF};
+use crate::types::gm::DRUM_CHANNEL;
+use crate::types::message::{ChannelVoiceMessage, MidiChannelMessage};
+use crate::types::rpn::{self, RegisteredParameter};
+use crate::types::state::Parameter;
+use crate::types::sysex::ALL_CALL;
+use crate::types::tuning::{frequency_to_key, Tuning, TuningMessage};
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// How MIDI Tuning Standard data is sent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MtsFormat {
+    /// Non-real-time bulk dump of all 128 keys
+    BulkDump,
+    /// Real-time single note tuning changes
+    SingleNote,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PitchBendTuningOptions {
+    /// Channels notes are rotated over. Messages on these channels are replaced.
+    pub channels: std::vec::Vec<u8>,
+    /// Pitch bend sensitivity in semitones set on every channel
+    pub bend_range: u8,
+}
+
+impl PitchBendTuningOptions {
+    /// Every channel except the drum channel, with a bend range of 2 semitones
+    pub fn new() -> PitchBendTuningOptions {
+        PitchBendTuningOptions {channels: (0..16).filter(|ch| *ch != DRUM_CHANNEL).collect(), bend_range: 2}
+    }
+}
+
+impl Default for PitchBendTuningOptions {
+    fn default() -> Self {
+        PitchBendTuningOptions::new()
+    }
+}
+
+/// A note placed on a pool channel by retune_pitch_bend
+struct Voice {
+    track: usize,
+    /// Channel and key of the original note
+    source: (u8, u8),
+    start: u32,
+    end: Option<u32>,
+    channel: u8,
+    key: u8,
+    /// Index of the NoteOff in the output of the track
+    off_index: Option<usize>,
+}
+
+fn channel_message(message: ChannelVoiceMessage) -> MidiEvent {
+    MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(message))
+}
+
+/// Prepends events at tick 0 of the first track, before anything already there
+fn prepend_events(smf: &mut SMF, events: std::vec::Vec<MidiEvent>) {
+    if smf.tracks().is_empty() {
+        // Cannot fail: the file has no tracks
+        let _ = smf.add_track(crate::types::event::TrackChunk::new(vec![]));
+    }
+    let track = &mut smf.tracks_mut()[0];
+    let mut all: std::vec::Vec<(u32, MidiEvent)> = events.into_iter().map(|event| (0, event)).collect();
+    all.extend(track.to_absolute());
+    *track = crate::types::event::TrackChunk::from_absolute(all);
+}
+
+impl SMF {
+    /// Retunes the file for synthesizers supporting the MIDI Tuning Standard.
+    /// The tuning is sent to the tuning program at tick 0 of the first track,
+    /// and every channel playing notes, except the drum channel, selects that program.
+    pub fn retune_mts(&mut self, tuning: &Tuning, program: u8, name: &str, format: MtsFormat) {
+        let mut channels = [false; 16];
+        for track in self.tracks() {
+            for note in track.notes() {
+                channels[(note.channel & 0x0F) as usize] = true;
+            }
+        }
+
+        let messages = match format {
+            MtsFormat::BulkDump => vec![TuningMessage::bulk_dump(ALL_CALL, program, name, tuning)],
+            MtsFormat::SingleNote => TuningMessage::single_note_changes(ALL_CALL, program, tuning),
+        };
+        let mut events: std::vec::Vec<MidiEvent> = messages.iter().map(|m| MidiEvent::SysExEvent(m.to_event())).collect();
+        for channel in (0..16u8).filter(|ch| channels[*ch as usize] && *ch != DRUM_CHANNEL) {
+            let value = rpn::join_14bit(program & 0x7F, 0);
+            events.extend(rpn::encode_parameter(channel, Parameter::Rpn(RegisteredParameter::TuningProgramSelect.number()), value, true).into_iter().map(MidiEvent::MidiChannelMessage));
+        }
+        prepend_events(self, events);
+    }
+
+    /// Retunes the file for any synthesizer by playing each note on the nearest key,
+    /// detuned by pitch bend on a channel of its own.
+    /// Notes of every channel in the pool are rotated over the whole pool, so this suits files where those channels share one instrument.
+    /// Their other channel messages are copied to every pool channel and their pitch bends are dropped.
+    /// Polyphonic key pressure follows the note it applies to. Notes on unmapped keys are removed.
+    /// When more notes sound at once than the pool has channels, the note ending first is cut off.
+    pub fn retune_pitch_bend(&mut self, tuning: &Tuning, opts: &PitchBendTuningOptions) -> Result<()> {
+        let pool: std::vec::Vec<u8> = opts.channels.iter().map(|ch| ch & 0x0F).collect();
+        if pool.is_empty() {
+            return Err(SmfError::new("no channels to retune notes on"));
+        }
+        if opts.bend_range == 0 {
+            return Err(SmfError::new("pitch bend range must be positive"));
+        }
+
+        let mut outputs: std::vec::Vec<std::vec::Vec<(u32, MidiEvent)>> = std::vec::Vec::new();
+        let mut notes: std::vec::Vec<(usize, Note)> = std::vec::Vec::new();
+        // (track, tick, channel, key, pressure)
+        let mut pressures: std::vec::Vec<(usize, u32, u8, u8, u8)> = std::vec::Vec::new();
+        for (index, track) in self.tracks().iter().enumerate() {
+            let mut output = std::vec::Vec::new();
+            for (tick, event) in track.to_absolute() {
+                let message = match &event {
+                    MidiEvent::MidiChannelMessage(message) if pool.contains(&message.channel()) => message,
+                    _ => {
+                        output.push((tick, event));
+                        continue;
+                    }
+                };
+                match message {
+                    MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOn{..})
+                    | MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOff{..})
+                    | MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::PitchBend{..}) => {},
+                    MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::PolyphonicKeyPressure{channel, key, vel}) => {
+                        pressures.push((index, tick, *channel, *key, *vel));
+                    },
+                    _ => {
+                        for channel in &pool {
+                            let mut copy = message.clone();
+                            copy.set_channel(*channel);
+                            output.push((tick, MidiEvent::MidiChannelMessage(copy)));
+                        }
+                    }
+                }
+            }
+            notes.extend(track.notes().into_iter().filter(|note| pool.contains(&note.channel)).map(|note| (index, note)));
+            outputs.push(output);
+        }
+        notes.sort_by_key(|(track, note)| (note.start, *track, note.on_index));
+
+        // Per pool channel: tick until which it sounds (None while a note never ends), when it was last used
+        // and the voice playing on it
+        let mut busy: std::vec::Vec<(Option<u32>, usize, Option<usize>)> = vec![(Some(0), 0, None); pool.len()];
+        let mut voices: std::vec::Vec<Voice> = std::vec::Vec::new();
+        for (order, (track, note)) in notes.into_iter().enumerate() {
+            let frequency = match tuning.frequency(note.key) {
+                Some(frequency) => frequency,
+                None => continue,
+            };
+            let target = frequency_to_key(frequency);
+            let key = target.round().clamp(0.0, 127.0);
+            let bend = 8192.0 + (target - key) / opts.bend_range as f64 * 8192.0;
+            let bend = bend.round().clamp(0.0, 16383.0) as u16;
+            let key = key as u8;
+
+            let slot = (0..pool.len())
+                .filter(|i| busy[*i].0.is_some_and(|end| end <= note.start))
+                .min_by_key(|i| busy[*i].1)
+                .unwrap_or_else(|| (0..pool.len()).min_by_key(|i| busy[*i].0.unwrap_or(u32::MAX)).unwrap());
+            let channel = pool[slot];
+
+            if let Some(stolen) = busy[slot].2.map(|v| &mut voices[v]) {
+                if stolen.end.is_none_or(|end| end > note.start) {
+                    // The pool is exhausted: end the note still sounding before its channel is retuned
+                    stolen.end = Some(note.start);
+                    let off = (note.start, channel_message(ChannelVoiceMessage::NoteOff{channel, key: stolen.key, vel: 0}));
+                    match stolen.off_index {
+                        Some(index) => outputs[stolen.track][index] = off,
+                        None => {
+                            stolen.off_index = Some(outputs[stolen.track].len());
+                            outputs[stolen.track].push(off);
+                        },
+                    }
+                }
+            }
+
+            let output = &mut outputs[track];
+            output.push((note.start, channel_message(ChannelVoiceMessage::pitch_bend(channel, bend))));
+            output.push((note.start, channel_message(ChannelVoiceMessage::NoteOn{channel, key, vel: note.velocity})));
+            let off_index = note.end.map(|end| {
+                output.push((end, channel_message(ChannelVoiceMessage::NoteOff{channel, key, vel: 0})));
+                output.len() - 1
+            });
+            busy[slot] = (note.end, order + 1, Some(voices.len()));
+            voices.push(Voice {track, source: (note.channel, note.key), start: note.start, end: note.end, channel, key, off_index});
+        }
+
+        for (track, tick, channel, key, vel) in pressures {
+            let voice = voices.iter().rev().find(|voice| voice.source == (channel, key) && voice.start <= tick && voice.end.is_none_or(|end| tick < end));
+            if let Some(voice) = voice {
+                outputs[track].push((tick, channel_message(ChannelVoiceMessage::PolyphonicKeyPressure{channel: voice.channel, key: voice.key, vel})));
+            }
+        }
+
+        let tracks = outputs.into_iter().map(super::rebuild_track).collect();
+        self.set_tracks(tracks)?;
+
+        let mut sensitivity = std::vec::Vec::new();
+        for channel in &pool {
+            sensitivity.extend(rpn::pitch_bend_sensitivity(*channel, opts.bend_range, 0).into_iter().map(MidiEvent::MidiChannelMessage));
+        }
+        prepend_events(self, sensitivity);
+        Ok(())
+    }
+}
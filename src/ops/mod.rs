@@ -0,0 +1,53 @@
+pub mod channel;
+pub mod cleanup;
+pub mod concat;
+pub mod humanize;
+pub mod microtonal;
+pub mod notes;
+pub mod optimize;
+pub mod query;
+pub mod quantize;
+pub mod resolution;
+pub mod slice;
+pub mod split;
+pub mod stretch;
+pub mod transpose;
+pub mod velocity;
+
+use crate::types::event::{MidiEvent, TrackChunk};
+use crate::types::message::{ChannelVoiceMessage, MetaEvent, MidiChannelMessage};
+
+pub(crate) fn note_off(channel: u8, key: u8) -> MidiEvent {
+    MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOff{channel, key, vel: 0}))
+}
+
+pub(crate) fn end_of_track() -> MidiEvent {
+    MidiEvent::MetaEvent(MetaEvent::EndOfTrack)
+}
+
+/// Builds a track from (absolute tick, event) pairs after their ticks have been edited.
+/// Events are stably sorted by tick with note starts placed after the other events on the same tick,
+/// and a single EndOfTrack is kept at the end if the input had one.
+pub(crate) fn rebuild_track(events: std::vec::Vec<(u32, MidiEvent)>) -> TrackChunk {
+    let mut end: Option<u32> = None;
+    let mut events: std::vec::Vec<(u32, MidiEvent)> = events.into_iter().filter(|(tick, event)| {
+        if event.is_end_of_track() {
+            end = Some(end.map_or(*tick, |e| e.max(*tick)));
+            false
+        } else {
+            true
+        }
+    }).collect();
+
+    events.sort_by_key(|(tick, event)| {
+        let is_start = matches!(notes::note_edge(event), Some(notes::NoteEdge::On{..}));
+        (*tick, is_start)
+    });
+
+    if let Some(end) = end {
+        let last = events.last().map_or(0, |(tick, _)| *tick);
+        events.push((end.max(last), end_of_track()));
+    }
+
+    TrackChunk::from_absolute(events)
+}
@@ -0,0 +1,70 @@
+use crate::types::event::{MidiEvent, TrackChunk};
+use crate::types::message::{ChannelVoiceMessage, MidiChannelMessage};
+
+/// A NoteOn paired with its NoteOff
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+    /// Absolute tick of the NoteOn
+    pub start: u32,
+    /// Absolute tick of the NoteOff. None if the note never ends.
+    pub end: Option<u32>,
+    /// Index of the NoteOn in TrackChunk::events
+    pub on_index: usize,
+    /// Index of the NoteOff in TrackChunk::events
+    pub off_index: Option<usize>,
+}
+
+impl Note {
+    pub fn duration(&self) -> Option<u32> {
+        self.end.map(|end| end - self.start)
+    }
+}
+
+/// Kind of a note event. NoteOn with velocity 0 is treated as NoteOff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoteEdge {
+    On{channel: u8, key: u8, vel: u8},
+    Off{channel: u8, key: u8},
+}
+
+pub fn note_edge(event: &MidiEvent) -> Option<NoteEdge> {
+    use ChannelVoiceMessage::*;
+    match event {
+        MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(cvm)) => match *cvm {
+            NoteOn{channel, key, vel} if vel > 0 => Some(NoteEdge::On{channel, key, vel}),
+            NoteOn{channel, key, ..} | NoteOff{channel, key, ..} => Some(NoteEdge::Off{channel, key}),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+impl TrackChunk {
+    /// Pairs NoteOn and NoteOff events. A NoteOff ends the earliest sounding note of the same channel and key.
+    /// NoteOffs without a sounding note are ignored. Notes are ordered by their NoteOn.
+    pub fn notes(&self) -> std::vec::Vec<Note> {
+        let mut notes: std::vec::Vec<Note> = std::vec::Vec::new();
+        let mut sounding: std::collections::HashMap<(u8, u8), std::collections::VecDeque<usize>> = std::collections::HashMap::new();
+
+        for (index, (tick, pair)) in self.absolute_ticks().into_iter().zip(self.events.iter()).enumerate() {
+            match note_edge(pair.event()) {
+                Some(NoteEdge::On{channel, key, vel}) => {
+                    sounding.entry((channel, key)).or_default().push_back(notes.len());
+                    notes.push(Note {channel, key, velocity: vel, start: tick, end: None, on_index: index, off_index: None});
+                },
+                Some(NoteEdge::Off{channel, key}) => {
+                    if let Some(n) = sounding.get_mut(&(channel, key)).and_then(|queue| queue.pop_front()) {
+                        notes[n].end = Some(tick);
+                        notes[n].off_index = Some(index);
+                    }
+                },
+                None => {}
+            }
+        }
+
+        notes
+    }
+}
@@ -0,0 +1,144 @@
+use crate::types::event::{MidiEvent, SMF};
+use crate::types::message::{ChannelVoiceMessage, MetaEvent, MidiChannelMessage};
+use crate::types::state::RESET_CONTROLLERS;
+
+/// Number of events and tracks removed by SMF::optimize
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptimizeReport {
+    pub controllers: usize,
+    pub programs: usize,
+    pub pitch_bends: usize,
+    pub tempos: usize,
+    pub empty_tracks: usize,
+}
+
+impl OptimizeReport {
+    pub fn total(&self) -> usize {
+        self.controllers + self.programs + self.pitch_bends + self.tempos + self.empty_tracks
+    }
+}
+
+/// Data entry and increment/decrement controllers act on the selected parameter every time they are sent
+const NON_STATE_CONTROLLERS: [u8; 4] = [6, 38, 96, 97];
+
+/// Output port of a track as set by SpecifyOutPort. None is the default port.
+type Port = Option<u8>;
+
+/// Values currently set on every channel of every port, shared by all tracks
+struct State {
+    controllers: std::collections::HashMap<(Port, u8, u8), u8>,
+    programs: std::collections::HashMap<(Port, u8), u8>,
+    pitch_bends: std::collections::HashMap<(Port, u8), (u8, u8)>,
+    tempo: Option<u32>,
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            controllers: std::collections::HashMap::new(),
+            programs: std::collections::HashMap::new(),
+            pitch_bends: std::collections::HashMap::new(),
+            tempo: None,
+        }
+    }
+
+    /// Returns true if the event does not change anything on the port. Otherwise the state is updated.
+    fn is_redundant(&mut self, port: Port, event: &MidiEvent, report: &mut OptimizeReport) -> bool {
+        use ChannelVoiceMessage::*;
+        match event {
+            MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(cvm)) => match *cvm {
+                ControlChange{channel, cc: 121, ..} => {
+                    // Reset All Controllers: forget the values it resets rather than guess their defaults
+                    self.controllers.retain(|(p, ch, cc), _| *p != port || *ch != channel || !RESET_CONTROLLERS.contains(cc));
+                    self.pitch_bends.remove(&(port, channel));
+                    false
+                },
+                ControlChange{channel, cc, value} if cc < 120 && !NON_STATE_CONTROLLERS.contains(&cc) => {
+                    let redundant = self.controllers.insert((port, channel, cc), value) == Some(value);
+                    if !redundant && (cc == 0 || cc == 32) {
+                        // A new bank takes effect with the next program change, even if the program number is the same
+                        self.programs.remove(&(port, channel));
+                    }
+                    report.controllers += redundant as usize;
+                    redundant
+                },
+                ProgramChange{channel, pc} => {
+                    let redundant = self.programs.insert((port, channel), pc) == Some(pc);
+                    report.programs += redundant as usize;
+                    redundant
+                },
+                PitchBend{channel, lsb, msb} => {
+                    let redundant = self.pitch_bends.insert((port, channel), (lsb, msb)) == Some((lsb, msb));
+                    report.pitch_bends += redundant as usize;
+                    redundant
+                },
+                _ => false
+            },
+            MidiEvent::MetaEvent(MetaEvent::SetTempo{tempo}) => {
+                let redundant = self.tempo.replace(*tempo) == Some(*tempo);
+                report.tempos += redundant as usize;
+                redundant
+            },
+            MidiEvent::SysExEvent(_) => {
+                // SysEx may reset the receivers on the port (GM/GS/XG reset)
+                self.controllers.retain(|(p, _, _), _| *p != port);
+                self.programs.retain(|(p, _), _| *p != port);
+                self.pitch_bends.retain(|(p, _), _| *p != port);
+                false
+            },
+            _ => false
+        }
+    }
+}
+
+/// Returns true if the track has nothing but EndOfTrack and naming meta events
+fn is_empty_track(events: &[(u32, MidiEvent)]) -> bool {
+    use MetaEvent::*;
+    events.iter().all(|(_, event)| matches!(event,
+        MidiEvent::MetaEvent(EndOfTrack | SequenceTrackName{..} | InstrumentName{..} | MIDIChannelPrefix{..} | SpecifyOutPort{..})))
+}
+
+impl SMF {
+    /// Removes controller, program, pitch bend and tempo events which set the value already in effect,
+    /// and tracks that contain no events. Events of all tracks are examined in time order;
+    /// channel state is kept separately for each output port set by SpecifyOutPort.
+    /// Use filerw::write_to_file_running_status to write the result compactly.
+    pub fn optimize(&mut self) -> OptimizeReport {
+        let mut report = OptimizeReport::default();
+        let tracks: std::vec::Vec<std::vec::Vec<(u32, MidiEvent)>> = self.tracks().iter().map(|track| track.to_absolute()).collect();
+
+        // (tick, track, index) in playback order
+        let mut order: std::vec::Vec<(u32, usize, usize)> = tracks.iter().enumerate()
+            .flat_map(|(t, events)| events.iter().enumerate().map(move |(i, (tick, _))| (*tick, t, i)))
+            .collect();
+        order.sort();
+
+        let mut state = State::new();
+        let mut ports: std::vec::Vec<Port> = vec![None; tracks.len()];
+        let mut redundant: std::vec::Vec<std::vec::Vec<bool>> = tracks.iter().map(|events| vec![false; events.len()]).collect();
+        for (_, t, i) in order {
+            let event = &tracks[t][i].1;
+            if let MidiEvent::MetaEvent(MetaEvent::SpecifyOutPort{port}) = event {
+                ports[t] = Some(*port);
+            }
+            redundant[t][i] = state.is_redundant(ports[t], event, &mut report);
+        }
+
+        for (t, track) in self.tracks_mut().iter_mut().enumerate() {
+            let mut flags = redundant[t].iter();
+            track.retain_events(|_| !flags.next().copied().unwrap_or(false));
+        }
+
+        let mut index = 0;
+        while index < self.tracks().len() && self.tracks().len() > 1 {
+            if is_empty_track(&self.tracks()[index].to_absolute()) {
+                self.remove_track(index);
+                report.empty_tracks += 1;
+            } else {
+                index += 1;
+            }
+        }
+
+        report
+    }
+}
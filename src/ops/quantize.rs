@@ -0,0 +1,115 @@
+use crate::file::filerw::SmfError;
+use crate::types::event::{TrackChunk, SMF};
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Quantize grid expressed in musical units
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// Note value of one grid step as a fraction of a whole note (4 = quarter, 16 = sixteenth)
+    pub division: u32,
+    /// Use triplets of the note value (three steps in the time of two)
+    pub triplet: bool,
+    /// Position of every second grid point within a pair of steps, in percent.
+    /// 50 is straight, about 66 is triplet feel.
+    pub swing: f64,
+}
+
+impl Grid {
+    pub fn new(division: u32) -> Grid {
+        Grid {division, triplet: false, swing: 50.0}
+    }
+
+    pub fn triplet(division: u32) -> Grid {
+        Grid {division, triplet: true, swing: 50.0}
+    }
+
+    /// Length of a grid step in ticks
+    pub fn step(&self, resolution: u16) -> f64 {
+        let step = resolution as f64 * 4.0 / self.division as f64;
+        if self.triplet { step * 2.0 / 3.0 } else { step }
+    }
+
+    /// Returns the grid point nearest to the tick
+    pub fn nearest(&self, resolution: u16, tick: u32) -> u32 {
+        let step = self.step(resolution);
+        let pair = step * 2.0;
+        let offbeat = pair * self.swing / 100.0;
+
+        let base = (tick as f64 / pair).floor() * pair;
+        let candidates = [base, base + offbeat, base + pair];
+        let nearest = candidates.iter().fold(base, |best, c| {
+            if (c - tick as f64).abs() < (best - tick as f64).abs() { *c } else { best }
+        });
+        nearest.round() as u32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizeOptions {
+    pub grid: Grid,
+    /// How far notes are moved toward the grid point (0.0 - 1.0)
+    pub strength: f64,
+    /// Notes farther from the grid point than this fraction of a step are not moved. None moves every note.
+    pub window: Option<f64>,
+    /// Snap note ends too. Otherwise note lengths are kept.
+    pub quantize_ends: bool,
+}
+
+impl QuantizeOptions {
+    /// Full strength, no window, starts only
+    pub fn new(grid: Grid) -> QuantizeOptions {
+        QuantizeOptions {grid, strength: 1.0, window: None, quantize_ends: false}
+    }
+
+    /// Returns the quantized tick, or None if it is outside the window
+    fn apply(&self, resolution: u16, tick: u32) -> Option<u32> {
+        let target = self.grid.nearest(resolution, tick) as f64;
+        let distance = target - tick as f64;
+        if let Some(window) = self.window {
+            if distance.abs() > window * self.grid.step(resolution) {
+                return None;
+            }
+        }
+        Some((tick as f64 + distance * self.strength).round().max(0.0) as u32)
+    }
+}
+
+impl TrackChunk {
+    /// Quantizes note timings. resolution is ticks per quarter note of the file.
+    /// Fails if the grid division or the resolution is 0.
+    pub fn quantize(&mut self, resolution: u16, options: &QuantizeOptions) -> Result<()> {
+        if options.grid.division == 0 || resolution == 0 {
+            return Err(SmfError::new("quantize grid division and resolution must be positive"));
+        }
+        let mut events = self.to_absolute();
+
+        for note in self.notes() {
+            let start = options.apply(resolution, note.start).unwrap_or(note.start);
+            events[note.on_index].0 = start;
+
+            if let (Some(end), Some(off)) = (note.end, note.off_index) {
+                let new_end = if options.quantize_ends {
+                    options.apply(resolution, end).unwrap_or(end)
+                } else {
+                    start + (end - note.start)
+                };
+                events[off].0 = new_end.max(start + 1);
+            }
+        }
+
+        *self = super::rebuild_track(events);
+        Ok(())
+    }
+}
+
+impl SMF {
+    /// Quantizes note timings in every track
+    pub fn quantize(&mut self, options: &QuantizeOptions) -> Result<()> {
+        let resolution = self.resolution();
+        for track in self.tracks_mut() {
+            track.quantize(resolution, options)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::types::event::{MidiEvent, SMF};
+use crate::types::message::{ChannelVoiceMessage, MidiChannelMessage};
+
+/// Kind of event matched by EventFilter
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// NoteOn and NoteOff, including NoteOn with velocity 0
+    Note,
+    /// NoteOn with velocity > 0
+    NoteOn,
+    /// NoteOff or NoteOn with velocity 0
+    NoteOff,
+    PolyphonicKeyPressure,
+    /// ControlChange of the given controller number, or of any controller if None
+    ControlChange(Option<u8>),
+    ProgramChange,
+    ChannelKeyPressure,
+    PitchBend,
+    ChannelMode,
+    /// Meta event of the given type byte, or any meta event if None
+    Meta(Option<u8>),
+    SysEx,
+}
+
+impl EventKind {
+    pub fn matches(&self, event: &MidiEvent) -> bool {
+        use ChannelVoiceMessage::*;
+        let cvm = match event {
+            MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(cvm)) => Some(cvm),
+            _ => None
+        };
+        match self {
+            EventKind::Note => matches!(cvm, Some(NoteOn{..} | NoteOff{..})),
+            EventKind::NoteOn => matches!(cvm, Some(NoteOn{vel, ..}) if *vel > 0),
+            EventKind::NoteOff => matches!(cvm, Some(NoteOff{..} | NoteOn{vel: 0, ..})),
+            EventKind::PolyphonicKeyPressure => matches!(cvm, Some(PolyphonicKeyPressure{..})),
+            EventKind::ControlChange(number) => matches!(cvm, Some(ControlChange{cc, ..}) if number.is_none_or(|n| n == *cc)),
+            EventKind::ProgramChange => matches!(cvm, Some(ProgramChange{..})),
+            EventKind::ChannelKeyPressure => matches!(cvm, Some(ChannelKeyPressure{..})),
+            EventKind::PitchBend => matches!(cvm, Some(PitchBend{..})),
+            EventKind::ChannelMode => matches!(event, MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelModeMessage(_))),
+            EventKind::Meta(kind) => matches!(event, MidiEvent::MetaEvent(meta) if kind.is_none_or(|k| k == meta.type_byte())),
+            EventKind::SysEx => matches!(event, MidiEvent::SysExEvent(_)),
+        }
+    }
+}
+
+/// Position of an event in a SMF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EventLocation {
+    pub track: usize,
+    /// Index in TrackChunk::events
+    pub index: usize,
+}
+
+/// An event found by SMF::query
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventMatch {
+    pub location: EventLocation,
+    /// Absolute tick
+    pub tick: u32,
+    pub event: MidiEvent,
+}
+
+/// Composable event filter. Every condition that is set must hold; kinds match if any of them does.
+///
+/// EventFilter::new().kind(EventKind::NoteOn).channel(9).keys(35..=36)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    kinds: std::vec::Vec<EventKind>,
+    channels: Option<std::vec::Vec<u8>>,
+    tracks: Option<std::vec::Vec<usize>>,
+    keys: Option<std::ops::RangeInclusive<u8>>,
+    velocities: Option<std::ops::RangeInclusive<u8>>,
+    ticks: Option<std::ops::Range<u32>>,
+}
+
+/// Returns key and velocity of note and key pressure events
+fn key_velocity(event: &MidiEvent) -> Option<(u8, u8)> {
+    use ChannelVoiceMessage::*;
+    match event {
+        MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(
+            NoteOn{key, vel, ..} | NoteOff{key, vel, ..} | PolyphonicKeyPressure{key, vel, ..}
+        )) => Some((*key, *vel)),
+        _ => None
+    }
+}
+
+impl EventFilter {
+    /// Matches every event
+    pub fn new() -> EventFilter {
+        EventFilter::default()
+    }
+
+    pub fn kind(mut self, kind: EventKind) -> EventFilter {
+        self.kinds.push(kind);
+        self
+    }
+
+    pub fn channel(mut self, channel: u8) -> EventFilter {
+        self.channels.get_or_insert_with(std::vec::Vec::new).push(channel);
+        self
+    }
+
+    pub fn track(mut self, track: usize) -> EventFilter {
+        self.tracks.get_or_insert_with(std::vec::Vec::new).push(track);
+        self
+    }
+
+    /// Only events with a key (notes and polyphonic key pressure) in the range match
+    pub fn keys(mut self, keys: std::ops::RangeInclusive<u8>) -> EventFilter {
+        self.keys = Some(keys);
+        self
+    }
+
+    /// Only events with a velocity (notes and polyphonic key pressure) in the range match
+    pub fn velocities(mut self, velocities: std::ops::RangeInclusive<u8>) -> EventFilter {
+        self.velocities = Some(velocities);
+        self
+    }
+
+    /// Only events at absolute ticks in the range match
+    pub fn ticks(mut self, ticks: std::ops::Range<u32>) -> EventFilter {
+        self.ticks = Some(ticks);
+        self
+    }
+
+    pub fn matches(&self, track: usize, tick: u32, event: &MidiEvent) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|kind| kind.matches(event)) {
+            return false;
+        }
+        if let Some(tracks) = &self.tracks {
+            if !tracks.contains(&track) {
+                return false;
+            }
+        }
+        if let Some(ticks) = &self.ticks {
+            if !ticks.contains(&tick) {
+                return false;
+            }
+        }
+        if let Some(channels) = &self.channels {
+            match event {
+                MidiEvent::MidiChannelMessage(message) if channels.contains(&message.channel()) => {},
+                _ => return false
+            }
+        }
+        if self.keys.is_some() || self.velocities.is_some() {
+            match key_velocity(event) {
+                Some((key, vel)) => {
+                    if self.keys.as_ref().is_some_and(|keys| !keys.contains(&key)) || self.velocities.as_ref().is_some_and(|vels| !vels.contains(&vel)) {
+                        return false;
+                    }
+                },
+                None => return false
+            }
+        }
+        true
+    }
+}
+
+impl SMF {
+    /// Returns every event matching the filter, ordered by track and index
+    pub fn query(&self, filter: &EventFilter) -> std::vec::Vec<EventMatch> {
+        let mut matched = std::vec::Vec::new();
+        for (t, track) in self.tracks().iter().enumerate() {
+            for (index, (tick, pair)) in track.absolute_ticks().into_iter().zip(track.events.iter()).enumerate() {
+                if filter.matches(t, tick, pair.event()) {
+                    matched.push(EventMatch {location: EventLocation {track: t, index}, tick, event: pair.event_copy()});
+                }
+            }
+        }
+        matched
+    }
+
+    /// Calls f with every matching event so that it can be edited in place. Returns the number of matches.
+    pub fn edit_matching<F>(&mut self, filter: &EventFilter, mut f: F) -> usize where F: FnMut(&mut MidiEvent) {
+        let mut count = 0;
+        for (t, track) in self.tracks_mut().iter_mut().enumerate() {
+            let ticks = track.absolute_ticks();
+            for (pair, tick) in track.events.iter_mut().zip(ticks) {
+                if filter.matches(t, tick, pair.event()) {
+                    f(pair.event_mut());
+                    count += 1;
+                }
+            }
+            track.recalculate_length();
+        }
+        count
+    }
+
+    /// Removes every matching event keeping the positions of the others. Returns the number of removed events.
+    pub fn remove_matching(&mut self, filter: &EventFilter) -> usize {
+        let mut count = 0;
+        for (t, track) in self.tracks_mut().iter_mut().enumerate() {
+            let mut ticks = track.absolute_ticks().into_iter();
+            track.retain_events(|event| {
+                let tick = ticks.next().unwrap_or(0);
+                let remove = filter.matches(t, tick, event) && !event.is_end_of_track();
+                count += remove as usize;
+                !remove
+            });
+        }
+        count
+    }
+
+    pub fn event_at(&self, location: EventLocation) -> Option<&MidiEvent> {
+        self.track(location.track).and_then(|track| track.get(location.index)).map(|pair| pair.event())
+    }
+
+    pub fn event_at_mut(&mut self, location: EventLocation) -> Option<&mut MidiEvent> {
+        self.track_mut(location.track).and_then(|track| track.get_mut(location.index)).map(|pair| pair.event_mut())
+    }
+}
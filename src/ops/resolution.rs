@@ -0,0 +1,62 @@
+use crate::file::filerw::SmfError;
+use crate::types::event::{TrackChunk, SMF};
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Rescales an absolute tick from one resolution to another, rounding half up.
+/// Returns None if from is 0 or the result does not fit in u32.
+pub fn rescale_tick(tick: u32, from: u16, to: u16) -> Option<u32> {
+    if from == 0 {
+        return None;
+    }
+    std::convert::TryFrom::try_from((tick as u64 * to as u64 + from as u64 / 2) / from as u64).ok()
+}
+
+fn check_resolution(resolution: u16) -> Result<()> {
+    if resolution == 0 {
+        Err(SmfError::new("resolution must not be 0"))
+    } else if resolution & 0x8000 != 0 {
+        Err(SmfError::new("SMPTE time division cannot be rescaled"))
+    } else {
+        Ok(())
+    }
+}
+
+impl TrackChunk {
+    /// Rescales delta times from one resolution to another.
+    /// Rounding is done on absolute ticks so that errors do not accumulate.
+    /// Fails without changing the track if a resolution is invalid or a tick overflows.
+    pub fn rescale_resolution(&mut self, from: u16, to: u16) -> Result<()> {
+        check_resolution(from)?;
+        check_resolution(to)?;
+        let events = self.to_absolute().into_iter()
+            .map(|(tick, event)| match rescale_tick(tick, from, to) {
+                Some(tick) => Ok((tick, event)),
+                None => Err(SmfError::new("rescaled tick does not fit in u32")),
+            })
+            .collect::<Result<std::vec::Vec<_>>>()?;
+        *self = TrackChunk::from_absolute(events);
+        Ok(())
+    }
+}
+
+impl SMF {
+    /// Changes ticks per quarter note and rescales every delta time accordingly
+    pub fn rescale_resolution(&mut self, resolution: u16) -> Result<()> {
+        let from = self.resolution();
+        check_resolution(from)?;
+        check_resolution(resolution)?;
+        if from == resolution {
+            return Ok(());
+        }
+
+        // Rescale copies so that an overflow leaves the file unchanged
+        let mut tracks = self.tracks().to_vec();
+        for track in &mut tracks {
+            track.rescale_resolution(from, resolution)?;
+        }
+        self.set_tracks(tracks)?;
+        self.set_resolution(resolution);
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+use crate::types::message::{ChannelVoiceMessage, MetaEvent, MidiChannelMessage};
+use crate::types::state::MidiState;
+
+/// Identifies an event which sets a value that stays in effect until it is set again
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StateKey {
+    Meta(u8),
+    Control{channel: u8, cc: u8},
+    Program(u8),
+    PitchBend(u8),
+    ChannelPressure(u8),
+}
+
+fn state_key(event: &MidiEvent) -> Option<StateKey> {
+    use ChannelVoiceMessage::*;
+    match event {
+        MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(cvm)) => match *cvm {
+            ControlChange{channel, cc, ..} if cc < 120 => Some(StateKey::Control{channel, cc}),
+            ProgramChange{channel, ..} => Some(StateKey::Program(channel)),
+            PitchBend{channel, ..} => Some(StateKey::PitchBend(channel)),
+            ChannelKeyPressure{channel, ..} => Some(StateKey::ChannelPressure(channel)),
+            _ => None
+        },
+        MidiEvent::MetaEvent(meta) => match meta {
+            MetaEvent::SequenceTrackName{..} => Some(StateKey::Meta(0x03)),
+            MetaEvent::InstrumentName{..} => Some(StateKey::Meta(0x04)),
+            MetaEvent::MIDIChannelPrefix{..} => Some(StateKey::Meta(0x20)),
+            MetaEvent::SpecifyOutPort{..} => Some(StateKey::Meta(0x21)),
+            MetaEvent::SetTempo{..} => Some(StateKey::Meta(0x51)),
+            MetaEvent::TimeSignature{..} => Some(StateKey::Meta(0x58)),
+            MetaEvent::KeySignature{..} => Some(StateKey::Meta(0x59)),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Meta events in effect after the given events, in the order they were last set
+fn meta_state<'a, I>(events: I) -> std::vec::Vec<MidiEvent> where I: Iterator<Item = &'a MidiEvent> {
+    let mut state: std::vec::Vec<(StateKey, MidiEvent)> = std::vec::Vec::new();
+    for event in events {
+        if let Some(key @ StateKey::Meta(_)) = state_key(event) {
+            state.retain(|(k, _)| *k != key);
+            state.push((key, event.clone()));
+        }
+    }
+    state.into_iter().map(|(_, event)| event).collect()
+}
+
+/// Events which recreate the meta and channel state in effect after the given events
+fn state_events(events: &[(u32, MidiEvent)]) -> std::vec::Vec<MidiEvent> {
+    let mut state = MidiState::new();
+    for (_, event) in events {
+        state.apply(event);
+    }
+    let mut result = meta_state(events.iter().map(|(_, event)| event));
+    for channel in 0..16u8 {
+        result.extend(state.channel(channel).restore_messages(channel).into_iter().map(MidiEvent::MidiChannelMessage));
+    }
+    result
+}
+
+impl TrackChunk {
+    /// Extracts events in [start, end) re-based to tick 0.
+    /// Controllers, programs, pitch bend, parameters and meta state in effect at start are placed at tick 0,
+    /// notes sounding at start are retriggered at tick 0 and notes that cross end are closed at end.
+    pub fn slice_ticks(&self, start: u32, end: u32) -> TrackChunk {
+        let length = end.saturating_sub(start);
+        let events = self.to_absolute();
+        let notes = self.notes();
+        let before = events.iter().take_while(|(tick, _)| *tick < start).count();
+
+        let mut skip = vec![false; events.len()];
+        let mut retriggered: std::vec::Vec<(u32, MidiEvent)> = std::vec::Vec::new();
+        let mut closing: std::vec::Vec<(u32, MidiEvent)> = std::vec::Vec::new();
+        for note in &notes {
+            if note.start >= end {
+                continue;
+            }
+            if note.start < start {
+                if note.end.is_some_and(|e| e <= start) {
+                    // Its NoteOff may be at start, which would otherwise be copied without its NoteOn
+                    if let Some(off) = note.off_index {
+                        skip[off] = true;
+                    }
+                    continue;
+                }
+                let on = ChannelVoiceMessage::NoteOn{channel: note.channel, key: note.key, vel: note.velocity};
+                retriggered.push((0, MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(on))));
+            }
+            if note.end.is_none_or(|e| e >= end) {
+                if let Some(off) = note.off_index {
+                    skip[off] = true;
+                }
+                closing.push((length, super::note_off(note.channel, note.key)));
+            }
+        }
+
+        let mut sliced: std::vec::Vec<(u32, MidiEvent)> = state_events(&events[..before]).into_iter().map(|event| (0, event)).collect();
+        sliced.extend(retriggered);
+        for (i, (tick, event)) in events.into_iter().enumerate() {
+            if skip[i] || tick < start || tick >= end || event.is_end_of_track() {
+                continue;
+            }
+            sliced.push((tick - start, event));
+        }
+        sliced.extend(closing);
+        sliced.push((length, super::end_of_track()));
+
+        TrackChunk::from_absolute(sliced)
+    }
+
+    /// Shifts every event at or after the tick by length. Ticks past u32::MAX are clamped.
+    pub fn insert_time(&mut self, at: u32, length: u32) {
+        let events = self.to_absolute().into_iter()
+            .map(|(tick, event)| if tick >= at { (tick.saturating_add(length), event) } else { (tick, event) })
+            .collect();
+        *self = TrackChunk::from_absolute(events);
+    }
+
+    /// Removes the time range [start, end) and moves the following events earlier.
+    /// State-setting events in the range are kept at start, notes starting in the range are removed
+    /// and notes sounding into the range are ended at start.
+    pub fn delete_time(&mut self, start: u32, end: u32) {
+        if end <= start {
+            return;
+        }
+        let length = end - start;
+        let events = self.to_absolute();
+
+        let mut removed = vec![false; events.len()];
+        for note in self.notes() {
+            if note.start >= start && note.start < end {
+                removed[note.on_index] = true;
+                if let Some(off) = note.off_index {
+                    removed[off] = true;
+                }
+            }
+        }
+
+        let result = events.into_iter().enumerate().filter_map(|(i, (tick, event))| {
+            if removed[i] {
+                None
+            } else if tick >= end {
+                Some((tick - length, event))
+            } else if tick >= start {
+                if event.is_end_of_track() || state_key(&event).is_some() || crate::ops::notes::note_edge(&event).is_some() {
+                    Some((start, event))
+                } else {
+                    None
+                }
+            } else {
+                Some((tick, event))
+            }
+        }).collect();
+        *self = TrackChunk::from_absolute(result);
+    }
+}
+
+impl SMF {
+    /// Extracts the tick range [start, end) into a new SMF. See TrackChunk::slice_ticks.
+    pub fn slice_ticks(&self, start: u32, end: u32) -> SMF {
+        let tracks = self.tracks().iter().map(|track| track.slice_ticks(start, end)).collect();
+        SMF::new(self.header().clone(), tracks)
+    }
+
+    /// Extracts the time range [start, end) in seconds into a new SMF
+    pub fn slice_seconds(&self, start: f64, end: f64) -> SMF {
+        let map = self.tempo_map();
+        self.slice_ticks(map.seconds_to_tick(start), map.seconds_to_tick(end))
+    }
+
+    /// Inserts empty time of length ticks at the tick in every track
+    pub fn insert_time(&mut self, at: u32, length: u32) {
+        for track in self.tracks_mut() {
+            track.insert_time(at, length);
+        }
+    }
+
+    /// Removes the tick range [start, end) from every track. See TrackChunk::delete_time.
+    pub fn delete_time(&mut self, start: u32, end: u32) {
+        for track in self.tracks_mut() {
+            track.delete_time(start, end);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::file::filerw::SmfError;
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+use crate::types::gm::{self, DRUM_CHANNEL};
+use crate::types::message::{ChannelVoiceMessage, MetaEvent, MidiChannelMessage};
+
+use super::notes::{note_edge, NoteEdge};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitBy {
+    /// One track per MIDI channel
+    Channel,
+    /// One track per program number. Channel 10 gets its own drum track.
+    Program,
+}
+
+/// Key of a split track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Group {
+    Channel(u8),
+    Program(u8),
+    Drums,
+}
+
+/// Returns true if the meta event describes its own track rather than the whole file
+fn is_track_meta(event: &MidiEvent) -> bool {
+    use MetaEvent::*;
+    matches!(event, MidiEvent::MetaEvent(SequenceTrackName{..} | InstrumentName{..} | MIDIChannelPrefix{..} | SpecifyOutPort{..}))
+}
+
+/// Returns true if the event belongs to the conductor track
+fn is_global(event: &MidiEvent) -> bool {
+    match event {
+        MidiEvent::MidiChannelMessage(_) => false,
+        MidiEvent::MetaEvent(_) => !is_track_meta(event) && !event.is_end_of_track(),
+        MidiEvent::SysExEvent(_) => true,
+    }
+}
+
+fn track_name(name: &str) -> MidiEvent {
+    let text = name.as_bytes().to_vec();
+    MidiEvent::MetaEvent(MetaEvent::SequenceTrackName{length: text.len() as u32, text})
+}
+
+fn group_name(group: Group, first_program: Option<u8>) -> String {
+    match group {
+        Group::Channel(channel) if channel == DRUM_CHANNEL => format!("Ch.{} Drums", channel + 1),
+        Group::Channel(channel) => match first_program {
+            Some(program) => format!("Ch.{} {}", channel + 1, gm::instrument_name(program)),
+            None => format!("Ch.{}", channel + 1),
+        },
+        Group::Program(program) => gm::instrument_name(program).to_string(),
+        Group::Drums => "Drums".to_string(),
+    }
+}
+
+/// First program number and (absolute tick, event) pairs of each split track
+type Groups = std::collections::BTreeMap<Group, (Option<u8>, std::vec::Vec<(u32, MidiEvent)>)>;
+
+/// Splits channel messages of a track into groups, keeping note pairs together
+fn split_channel_events(track: &TrackChunk, by: SplitBy) -> Groups {
+    let mut groups = Groups::new();
+    let mut programs = [0u8; 16];
+    let mut sounding: std::collections::HashMap<(u8, u8), std::collections::VecDeque<Group>> = std::collections::HashMap::new();
+
+    for (tick, event) in track.to_absolute() {
+        let message = match &event {
+            MidiEvent::MidiChannelMessage(message) => message,
+            _ => continue,
+        };
+        let channel = message.channel();
+        if let MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ProgramChange{pc, ..}) = message {
+            programs[channel as usize] = *pc;
+        }
+
+        let current = match by {
+            SplitBy::Channel => Group::Channel(channel),
+            SplitBy::Program if channel == DRUM_CHANNEL => Group::Drums,
+            SplitBy::Program => Group::Program(programs[channel as usize]),
+        };
+        let group = match note_edge(&event) {
+            Some(NoteEdge::On{key, ..}) => {
+                sounding.entry((channel, key)).or_default().push_back(current);
+                current
+            },
+            Some(NoteEdge::Off{key, ..}) => sounding.get_mut(&(channel, key)).and_then(|queue| queue.pop_front()).unwrap_or(current),
+            None => current,
+        };
+
+        let entry = groups.entry(group).or_insert((None, std::vec::Vec::new()));
+        if let MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ProgramChange{pc, ..}) = message {
+            entry.0.get_or_insert(*pc);
+        }
+        entry.1.push((tick, event));
+    }
+
+    groups
+}
+
+impl SMF {
+    /// Splits tracks by channel or by program number.
+    /// Tempo, time signature and other global events are moved to a conductor track at index 0,
+    /// and each split track gets a SequenceTrackName generated from GM instrument names,
+    /// prefixed with the original name of tracks other than the first. Tracks that did not need splitting keep their name.
+    /// InstrumentName, MIDIChannelPrefix and SpecifyOutPort are copied to every track split from their track.
+    /// The file becomes format 1.
+    /// Fails if the result has more tracks than the header can count.
+    pub fn split_tracks(&mut self, by: SplitBy) -> Result<(), SmfError> {
+        let end = self.end_tick();
+        let mut conductor: std::vec::Vec<(u32, MidiEvent)> = std::vec::Vec::new();
+        let mut split: std::vec::Vec<TrackChunk> = std::vec::Vec::new();
+
+        for (index, track) in self.tracks().iter().enumerate() {
+            let name = track.name();
+            if index == 0 {
+                if let Some(name) = &name {
+                    conductor.push((0, track_name(name)));
+                }
+            }
+            conductor.extend(track.to_absolute().into_iter().filter(|(_, event)| is_global(event)));
+            // Instrument names, channel prefixes and ports are copied to every track split from this one
+            let track_meta: std::vec::Vec<(u32, MidiEvent)> = track.to_absolute().into_iter()
+                .filter(|(_, event)| is_track_meta(event) && !matches!(event, MidiEvent::MetaEvent(MetaEvent::SequenceTrackName{..})))
+                .collect();
+
+            let groups = split_channel_events(track, by);
+            let single = groups.len() == 1;
+            for (group, (first_program, events)) in groups {
+                let name = match &name {
+                    Some(name) if index != 0 && single => name.clone(),
+                    Some(name) if index != 0 => format!("{}: {}", name, group_name(group, first_program)),
+                    _ => group_name(group, first_program),
+                };
+                let mut events = events;
+                events.splice(0..0, track_meta.iter().cloned());
+                events.insert(0, (0, track_name(&name)));
+                events.push((end, MidiEvent::MetaEvent(MetaEvent::EndOfTrack)));
+                split.push(TrackChunk::from_absolute(events));
+            }
+        }
+
+        conductor.push((end, MidiEvent::MetaEvent(MetaEvent::EndOfTrack)));
+        split.insert(0, TrackChunk::from_absolute(conductor));
+        self.set_tracks(split)?;
+        self.set_format(1);
+        Ok(())
+    }
+}
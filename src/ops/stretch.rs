@@ -0,0 +1,97 @@
+use crate::file::filerw::SmfError;
+use crate::types::event::{EventPair, MidiEvent, TrackChunk, SMF};
+use crate::types::message::MetaEvent;
+use crate::types::tempo::DEFAULT_TEMPO;
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Largest tempo a SetTempo meta event can hold
+const MAX_TEMPO: u32 = 0xFF_FFFF;
+
+/// How playback speed is changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StretchMode {
+    /// Rewrite SetTempo events. Tick positions are kept.
+    Tempo,
+    /// Rescale tick positions. Tempo events are kept.
+    Ticks,
+}
+
+impl TrackChunk {
+    /// Multiplies every absolute tick by ratio, rounding on absolute ticks
+    pub fn scale_ticks(&mut self, ratio: f64) {
+        let events = self.to_absolute().into_iter()
+            .map(|(tick, event)| ((tick as f64 * ratio).round() as u32, event))
+            .collect();
+        *self = TrackChunk::from_absolute(events);
+    }
+}
+
+impl SMF {
+    /// Changes playback speed. speed > 1 plays faster, e.g. 0.5 is half speed.
+    /// Fails without changing the file if a scaled tempo does not fit in a SetTempo event
+    /// or a scaled tick does not fit in u32.
+    pub fn change_speed(&mut self, speed: f64, mode: StretchMode) -> Result<()> {
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(SmfError::new(&format!("invalid speed {}", speed)));
+        }
+
+        match mode {
+            StretchMode::Tempo => {
+                let scale = |tempo: u32| {
+                    let scaled = (tempo as f64 / speed).round();
+                    if scaled < 1.0 || scaled > MAX_TEMPO as f64 {
+                        return Err(SmfError::new(&format!("tempo {} scaled by speed {} is out of range", tempo, speed)));
+                    }
+                    Ok(scaled as u32)
+                };
+
+                // Check every tempo before changing any
+                let mut has_initial = false;
+                for track in self.tracks() {
+                    for (tick, event) in track.to_absolute() {
+                        if let MidiEvent::MetaEvent(MetaEvent::SetTempo{tempo}) = event {
+                            scale(tempo)?;
+                            has_initial |= tick == 0;
+                        }
+                    }
+                }
+                // The default tempo applies until the first SetTempo, so it has to be written explicitly
+                let initial = if has_initial { None } else { Some(scale(DEFAULT_TEMPO)?) };
+                if initial.is_some() && self.tracks().is_empty() {
+                    return Err(SmfError::new("no track to write tempo"));
+                }
+
+                for track in self.tracks_mut() {
+                    for pair in track.events.iter_mut() {
+                        if let MidiEvent::MetaEvent(MetaEvent::SetTempo{tempo}) = pair.event_mut() {
+                            *tempo = scale(*tempo)?;
+                        }
+                    }
+                }
+                if let (Some(tempo), Some(track)) = (initial, self.track_mut(0)) {
+                    track.events.insert(0, EventPair::new(0, MidiEvent::MetaEvent(MetaEvent::SetTempo{tempo})));
+                    track.recalculate_length();
+                }
+            },
+            StretchMode::Ticks => {
+                if (self.end_tick() as f64 / speed).round() > u32::MAX as f64 {
+                    return Err(SmfError::new(&format!("file is too long to slow down by speed {}", speed)));
+                }
+                for track in self.tracks_mut() {
+                    track.scale_ticks(1.0 / speed);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Changes playback speed so that the file lasts the given number of seconds
+    pub fn fit_duration(&mut self, seconds: f64, mode: StretchMode) -> Result<()> {
+        let current = self.duration_seconds();
+        if current <= 0.0 || !seconds.is_finite() || seconds <= 0.0 {
+            return Err(SmfError::new("cannot fit an empty file or to a non-positive duration"));
+        }
+        self.change_speed(current / seconds, mode)
+    }
+}
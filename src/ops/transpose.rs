@@ -0,0 +1,115 @@
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+use crate::types::message::{ChannelVoiceMessage, MetaEvent, MidiChannelMessage};
+
+pub use crate::types::gm::DRUM_CHANNEL;
+
+/// How to handle notes that fall outside 0-127 after transposition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutOfRange {
+    /// Clamp to 0 or 127
+    Clamp,
+    /// Remove the note events
+    Drop,
+    /// Move by octaves until the note fits in range
+    OctaveWrap,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransposeOptions {
+    pub semitones: i32,
+    /// Leave channel 10 (GM drums) untouched
+    pub skip_drums: bool,
+    pub out_of_range: OutOfRange,
+    /// Rewrite KeySignature meta events
+    pub update_key_signature: bool,
+}
+
+impl TransposeOptions {
+    /// Skips drums, clamps out-of-range notes and keeps key signatures
+    pub fn new(semitones: i32) -> TransposeOptions {
+        TransposeOptions {
+            semitones,
+            skip_drums: true,
+            out_of_range: OutOfRange::Clamp,
+            update_key_signature: false,
+        }
+    }
+}
+
+/// Returns the transposed key, or None if the note should be dropped
+pub fn transpose_key(key: u8, semitones: i32, policy: OutOfRange) -> Option<u8> {
+    let moved = key as i32 + semitones;
+    if (0..=127).contains(&moved) {
+        return Some(moved as u8);
+    }
+
+    match policy {
+        OutOfRange::Clamp => Some(moved.clamp(0, 127) as u8),
+        OutOfRange::Drop => None,
+        OutOfRange::OctaveWrap => {
+            let mut wrapped = moved;
+            while wrapped > 127 {
+                wrapped -= 12;
+            }
+            while wrapped < 0 {
+                wrapped += 12;
+            }
+            Some(wrapped as u8)
+        }
+    }
+}
+
+/// Returns the KeySignature sf field after transposition.
+/// sf is a two's complement number of sharps (positive) or flats (negative).
+/// The result is normalized to -5 (5 flats) ..= 6 (6 sharps).
+pub fn transpose_key_signature(sf: u8, semitones: i32) -> u8 {
+    let fifths = (sf as i8) as i32 + 7 * semitones;
+    let normalized = fifths.rem_euclid(12);
+    let normalized = if normalized > 6 { normalized - 12 } else { normalized };
+    (normalized as i8) as u8
+}
+
+impl TrackChunk {
+    /// Transposes NoteOn, NoteOff and PolyphonicKeyPressure keys
+    pub fn transpose(&mut self, options: &TransposeOptions) {
+        use ChannelVoiceMessage::*;
+
+        let mut dropped = false;
+        let mut keep = std::vec::Vec::with_capacity(self.events.len());
+        for pair in &mut self.events {
+            let mut retain = true;
+            match pair.event_mut() {
+                MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(
+                    NoteOn{channel, key, ..} | NoteOff{channel, key, ..} | PolyphonicKeyPressure{channel, key, ..}
+                )) if !(options.skip_drums && *channel == DRUM_CHANNEL) => {
+                    match transpose_key(*key, options.semitones, options.out_of_range) {
+                        Some(k) => *key = k,
+                        None => retain = false,
+                    }
+                },
+                MidiEvent::MetaEvent(MetaEvent::KeySignature{sf, ..}) if options.update_key_signature => {
+                    *sf = transpose_key_signature(*sf, options.semitones);
+                },
+                _ => {}
+            }
+            dropped |= !retain;
+            keep.push(retain);
+        }
+
+        if dropped {
+            let mut flags = keep.into_iter();
+            self.retain_events(|_| flags.next().unwrap_or(true));
+        } else {
+            self.recalculate_length();
+        }
+    }
+}
+
+impl SMF {
+    /// Transposes every track
+    pub fn transpose(&mut self, options: &TransposeOptions) {
+        for track in self.tracks_mut() {
+            track.transpose(options);
+        }
+    }
+}
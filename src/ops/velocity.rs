@@ -0,0 +1,60 @@
+use crate::types::event::{MidiEvent, TrackChunk, SMF};
+use crate::types::message::{ChannelVoiceMessage, MidiChannelMessage};
+
+/// Velocity transformation applied to NoteOn events.
+/// Results are clamped to 1-127 so that a NoteOn never turns into a NoteOff.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VelocityCurve {
+    /// vel * factor + offset
+    Scale{factor: f64, offset: i32},
+    /// Every note gets the same velocity
+    Fixed(u8),
+    /// pivot + (vel - pivot) * ratio. ratio < 1 compresses, ratio > 1 expands.
+    Compress{pivot: u8, ratio: f64},
+    /// Lookup table indexed by the original velocity
+    Table(std::vec::Vec<u8>),
+}
+
+impl VelocityCurve {
+    /// Builds a lookup table of 127 * (vel / 127) ^ exponent.
+    /// exponent < 1 makes soft notes louder, exponent > 1 makes them softer.
+    pub fn power(exponent: f64) -> VelocityCurve {
+        let table = (0..128).map(|vel| (127.0 * (vel as f64 / 127.0).powf(exponent)).round() as u8).collect();
+        VelocityCurve::Table(table)
+    }
+
+    pub fn apply(&self, vel: u8) -> u8 {
+        let value = match self {
+            VelocityCurve::Scale{factor, offset} => (vel as f64 * factor).round() as i32 + offset,
+            VelocityCurve::Fixed(value) => *value as i32,
+            VelocityCurve::Compress{pivot, ratio} => {
+                let pivot = *pivot as f64;
+                (pivot + (vel as f64 - pivot) * ratio).round() as i32
+            },
+            VelocityCurve::Table(table) => table.get(vel as usize).map_or(vel, |v| *v) as i32,
+        };
+        value.clamp(1, 127) as u8
+    }
+}
+
+impl TrackChunk {
+    /// Applies the curve to NoteOn velocities. If channels is given, only those channels are changed.
+    pub fn apply_velocity(&mut self, curve: &VelocityCurve, channels: Option<&[u8]>) {
+        for pair in &mut self.events {
+            if let MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOn{channel, vel, ..})) = pair.event_mut() {
+                if *vel > 0 && channels.is_none_or(|chs| chs.contains(channel)) {
+                    *vel = curve.apply(*vel);
+                }
+            }
+        }
+    }
+}
+
+impl SMF {
+    /// Applies the curve to NoteOn velocities of every track
+    pub fn apply_velocity(&mut self, curve: &VelocityCurve, channels: Option<&[u8]>) {
+        for track in self.tracks_mut() {
+            track.apply_velocity(curve, channels);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/// Source of time for timing-dependent code
+pub trait Clock {
+    /// Seconds since a fixed origin
+    fn now(&self) -> f64;
+
+    /// Waits until the time
+    fn sleep_until(&mut self, time: f64);
+}
+
+/// Wall clock measured from its creation
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: std::time::Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {origin: std::time::Instant::now()}
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        self.origin.elapsed().as_secs_f64()
+    }
+
+    fn sleep_until(&mut self, time: f64) {
+        let wait = time - self.now();
+        if wait > 0.0 {
+            std::thread::sleep(std::time::Duration::from_secs_f64(wait));
+        }
+    }
+}
+
+/// Virtual clock which only moves when told to. Clones share the same time,
+/// so a test can keep one and advance it while the code under test owns another.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    time: std::rc::Rc<std::cell::Cell<f64>>,
+}
+
+impl ManualClock {
+    /// Creates a clock at time 0
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn set(&self, time: f64) {
+        self.time.set(time);
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        self.time.set(self.time.get() + seconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.time.get()
+    }
+
+    /// Jumps to the time without waiting. Never goes backwards.
+    fn sleep_until(&mut self, time: f64) {
+        if time > self.time.get() {
+            self.time.set(time);
+        }
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &mut C {
+    fn now(&self) -> f64 {
+        (**self).now()
+    }
+
+    fn sleep_until(&mut self, time: f64) {
+        (**self).sleep_until(time);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod clock;
+pub mod output;
+pub mod sequencer;
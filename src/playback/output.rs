@@ -0,0 +1,45 @@
+use crate::types::message::{MidiChannelMessage, SysExEvent, SystemCommonMessage, SystemRealTimeMessage};
+
+/// Message sent to an output during playback
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputMessage {
+    Channel(MidiChannelMessage),
+    SysEx(SysExEvent),
+    Common(SystemCommonMessage),
+    RealTime(SystemRealTimeMessage),
+}
+
+/// Destination of played messages, such as a MIDI port or a software synthesizer.
+/// Messages are sent at the moment they should sound.
+pub trait MidiOutput {
+    fn send(&mut self, message: &OutputMessage);
+}
+
+/// Output which keeps every message it receives
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingOutput {
+    pub messages: std::vec::Vec<OutputMessage>,
+}
+
+impl RecordingOutput {
+    pub fn new() -> RecordingOutput {
+        RecordingOutput::default()
+    }
+
+    /// Removes and returns the messages received so far
+    pub fn take(&mut self) -> std::vec::Vec<OutputMessage> {
+        std::mem::take(&mut self.messages)
+    }
+}
+
+impl MidiOutput for RecordingOutput {
+    fn send(&mut self, message: &OutputMessage) {
+        self.messages.push(message.clone());
+    }
+}
+
+impl<O: MidiOutput + ?Sized> MidiOutput for &mut O {
+    fn send(&mut self, message: &OutputMessage) {
+        (**self).send(message);
+    }
+}
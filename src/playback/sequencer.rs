@@ -0,0 +1,409 @@
+use super::clock::{Clock, SystemClock};
+use super::output::{MidiOutput, OutputMessage};
+use crate::types::event::{MidiEvent, SMF};
+use crate::types::message::{ChannelModeMessage, ChannelVoiceMessage, MidiChannelMessage, SystemCommonMessage, SystemRealTimeMessage};
+use crate::types::state::MidiState;
+use crate::types::tempo::TempoMap;
+
+/// MIDI clock pulses per quarter note
+pub const CLOCKS_PER_QUARTER: u32 = 24;
+
+/// MIDI clock pulses per MIDI beat (sixteenth note), the unit of Song Position Pointer
+const CLOCKS_PER_SIXTEENTH: u64 = CLOCKS_PER_QUARTER as u64 / 4;
+
+/// Playback status of a Sequencer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaybackStatus {
+    Stopped,
+    Playing,
+    Paused,
+}
+
+/// A message with its position in the song
+#[derive(Debug, Clone, PartialEq)]
+struct ScheduledMessage {
+    tick: u32,
+    /// Song time at normal speed
+    seconds: f64,
+    message: OutputMessage,
+}
+
+/// Plays an SMF to a MidiOutput in real time, timed by a Clock.
+/// update must be called often enough (for example, at next_event_time) for messages to be sent on time;
+/// run does this until playback stops.
+pub struct Sequencer<O: MidiOutput, C: Clock = SystemClock> {
+    smf: SMF,
+    tempo_map: TempoMap,
+    messages: std::vec::Vec<ScheduledMessage>,
+    output: O,
+    clock: C,
+    status: PlaybackStatus,
+    /// Song time at anchor
+    position: f64,
+    /// Clock time position was taken at
+    anchor: f64,
+    /// Index of the next message to send
+    next: usize,
+    speed: f64,
+    /// Loop range as [start, end) ticks
+    loop_range: Option<(u32, u32)>,
+    /// Song time of the end of the longest track
+    end: f64,
+    /// Channels the song uses
+    channels: [bool; 16],
+    /// (channel, key) of notes started and not yet ended
+    sounding: std::vec::Vec<(u8, u8)>,
+    /// State of the channels as set by the messages sent so far
+    sent: MidiState,
+    /// Whether MIDI clock and Start/Continue/Stop are sent
+    send_clock: bool,
+    /// Number of the next MIDI clock pulse, counted from the beginning of the song
+    next_pulse: u64,
+}
+
+impl<O: MidiOutput> Sequencer<O, SystemClock> {
+    pub fn new(smf: &SMF, output: O) -> Sequencer<O, SystemClock> {
+        Sequencer::with_clock(smf, output, SystemClock::new())
+    }
+}
+
+impl<O: MidiOutput, C: Clock> Sequencer<O, C> {
+    pub fn with_clock(smf: &SMF, output: O, clock: C) -> Sequencer<O, C> {
+        let tempo_map = smf.tempo_map();
+        let mut messages: std::vec::Vec<(u32, usize, OutputMessage)> = std::vec::Vec::new();
+        let mut channels = [false; 16];
+        for (t, track) in smf.tracks().iter().enumerate() {
+            for (tick, event) in track.to_absolute() {
+                let message = match event {
+                    MidiEvent::MidiChannelMessage(message) => {
+                        channels[(message.channel() & 0x0F) as usize] = true;
+                        OutputMessage::Channel(message)
+                    },
+                    MidiEvent::SysExEvent(sysex) => OutputMessage::SysEx(sysex),
+                    MidiEvent::MetaEvent(_) => continue,
+                };
+                messages.push((tick, t, message));
+            }
+        }
+        // Stable: keeps the order inside each track
+        messages.sort_by_key(|(tick, t, _)| (*tick, *t));
+        let messages = messages.into_iter()
+            .map(|(tick, _, message)| ScheduledMessage {tick, seconds: tempo_map.tick_to_seconds(tick), message})
+            .collect();
+
+        Sequencer {
+            smf: smf.clone(),
+            end: tempo_map.tick_to_seconds(smf.end_tick()),
+            tempo_map,
+            messages,
+            output,
+            clock,
+            status: PlaybackStatus::Stopped,
+            position: 0.0,
+            anchor: 0.0,
+            next: 0,
+            speed: 1.0,
+            loop_range: None,
+            channels,
+            sounding: std::vec::Vec::new(),
+            sent: MidiState::new(),
+            send_clock: false,
+            next_pulse: 0,
+        }
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        self.status
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    pub fn into_output(self) -> O {
+        self.output
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    /// Length of the song in seconds at normal speed
+    pub fn duration(&self) -> f64 {
+        self.end
+    }
+
+    /// Current song time in seconds
+    pub fn position(&self) -> f64 {
+        match self.status {
+            PlaybackStatus::Playing => self.position + (self.clock.now() - self.anchor).max(0.0) * self.speed,
+            _ => self.position,
+        }
+    }
+
+    /// Current song position in ticks
+    pub fn position_tick(&self) -> u32 {
+        self.tempo_map.seconds_to_tick(self.position())
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Sets the playback speed relative to the file's tempo. 2.0 plays twice as fast.
+    pub fn set_speed(&mut self, speed: f64) {
+        if !speed.is_finite() || speed <= 0.0 {
+            return;
+        }
+        self.position = self.position();
+        self.anchor = self.clock.now();
+        self.speed = speed;
+    }
+
+    pub fn loop_range(&self) -> Option<(u32, u32)> {
+        self.loop_range
+    }
+
+    /// Repeats [start, end) ticks while playing. Empty ranges disable looping.
+    pub fn set_loop(&mut self, range: Option<(u32, u32)>) {
+        self.loop_range = range.filter(|(start, end)| start < end);
+    }
+
+    /// Sends MIDI clock at 24 pulses per quarter note following the tempo map,
+    /// with Start, Continue and Stop on play, resume, pause and stop.
+    /// Song Position Pointer is sent before Continue, and with Stop and Continue when seeking while playing.
+    pub fn set_send_clock(&mut self, send_clock: bool) {
+        self.send_clock = send_clock;
+    }
+
+    /// Starts or resumes playback
+    pub fn play(&mut self) {
+        if self.status != PlaybackStatus::Playing {
+            if self.send_clock {
+                if self.status == PlaybackStatus::Stopped && self.position == 0.0 {
+                    self.output.send(&OutputMessage::RealTime(SystemRealTimeMessage::Start));
+                } else {
+                    self.send_song_position();
+                    self.output.send(&OutputMessage::RealTime(SystemRealTimeMessage::Continue));
+                }
+            }
+            self.status = PlaybackStatus::Playing;
+            self.anchor = self.clock.now();
+        }
+    }
+
+    /// Pauses playback, ending the sounding notes. Playback resumes from the same position.
+    pub fn pause(&mut self) {
+        if self.status == PlaybackStatus::Playing {
+            self.position = self.position();
+            self.status = PlaybackStatus::Paused;
+            self.send_transport_stop();
+            self.silence(false);
+        }
+    }
+
+    /// Stops playback, sends all-notes-off and rewinds to the beginning
+    pub fn stop(&mut self) {
+        if self.status == PlaybackStatus::Playing {
+            self.send_transport_stop();
+        }
+        if self.status != PlaybackStatus::Stopped {
+            self.silence(true);
+        }
+        self.status = PlaybackStatus::Stopped;
+        self.position = 0.0;
+        self.next = 0;
+        self.next_pulse = 0;
+    }
+
+    /// Moves to the tick. Sounding notes are ended and the controllers, programs and parameters
+    /// in effect at the tick are sent, so playback continues as if it had started from the beginning.
+    /// Bank, program, controllers and RPNs sent before but not set at the tick are returned to their defaults.
+    pub fn seek_tick(&mut self, tick: u32) {
+        let seconds = self.tempo_map.tick_to_seconds(tick);
+        self.locate(seconds);
+    }
+
+    /// Moves to the song time in seconds. See seek_tick.
+    pub fn seek_seconds(&mut self, seconds: f64) {
+        self.locate(seconds.max(0.0));
+    }
+
+    /// Clock time at which update has something to do, or None if not playing
+    pub fn next_event_time(&self) -> Option<f64> {
+        if self.status != PlaybackStatus::Playing {
+            return None;
+        }
+        let mut due = self.end;
+        if let Some(message) = self.messages.get(self.next) {
+            due = due.min(message.seconds);
+        }
+        if let Some((_, end)) = self.loop_range {
+            due = due.min(self.tempo_map.tick_to_seconds(end));
+        }
+        if self.send_clock {
+            due = due.min(self.pulse_seconds(self.next_pulse));
+        }
+        Some(self.anchor + (due - self.position).max(0.0) / self.speed)
+    }
+
+    /// Sends the messages due at the current time. Playback stops at the end of the song unless looping.
+    pub fn update(&mut self) {
+        if self.status != PlaybackStatus::Playing {
+            return;
+        }
+        loop {
+            let song = self.position();
+            if let Some((start, end)) = self.loop_range {
+                let loop_end = self.tempo_map.tick_to_seconds(end);
+                if song >= loop_end {
+                    self.send_until(loop_end, false, |m| m.tick < end);
+                    let loop_start = self.tempo_map.tick_to_seconds(start);
+                    self.locate(loop_start);
+                    // Keep the time played past the loop end
+                    self.position = loop_start + (song - loop_end);
+                    continue;
+                }
+            }
+            self.send_until(song, true, |m| m.seconds <= song);
+            if song >= self.end && self.next >= self.messages.len() {
+                self.stop();
+            }
+            return;
+        }
+    }
+
+    /// Plays the whole song, sleeping on the clock between messages. Returns when playback stops.
+    pub fn run(&mut self) {
+        self.play();
+        while let Some(due) = self.next_event_time() {
+            self.clock.sleep_until(due);
+            self.update();
+        }
+    }
+
+    /// Sends messages for which due returns true, and clock pulses up to the song time (excluding it unless inclusive), in time order
+    fn send_until<F>(&mut self, song: f64, inclusive: bool, due: F) where F: Fn(&ScheduledMessage) -> bool {
+        loop {
+            let message = self.messages.get(self.next).filter(|m| due(m)).map(|m| m.seconds);
+            let pulse = Some(self.pulse_seconds(self.next_pulse))
+                .filter(|s| self.send_clock && (*s < song || (inclusive && *s == song)));
+            match (message, pulse) {
+                (Some(m), Some(p)) if p <= m => self.send_pulse(),
+                (None, Some(_)) => self.send_pulse(),
+                (Some(_), _) => {
+                    let message = self.messages[self.next].message.clone();
+                    self.next += 1;
+                    self.send(&message);
+                },
+                _ => return,
+            }
+        }
+    }
+
+    fn send_pulse(&mut self) {
+        self.next_pulse += 1;
+        self.output.send(&OutputMessage::RealTime(SystemRealTimeMessage::TimingClock));
+    }
+
+    /// Sends the position of the next pulse, which is on a sixteenth note after locate
+    fn send_song_position(&mut self) {
+        let beats = (self.next_pulse / CLOCKS_PER_SIXTEENTH).min(0x3FFF) as u16;
+        self.output.send(&OutputMessage::Common(SystemCommonMessage::SongPosition{beats}));
+    }
+
+    fn send_transport_stop(&mut self) {
+        if self.send_clock {
+            self.output.send(&OutputMessage::RealTime(SystemRealTimeMessage::Stop));
+        }
+    }
+
+    /// Song time of a MIDI clock pulse
+    fn pulse_seconds(&self, pulse: u64) -> f64 {
+        let resolution = self.tempo_map.resolution() as f64;
+        let tick = pulse as f64 * resolution / CLOCKS_PER_QUARTER as f64;
+        let whole = tick.floor() as u32;
+        let tempo = self.tempo_map.tempo_at(whole) as f64;
+        self.tempo_map.tick_to_seconds(whole) + (tick - whole as f64) * tempo / 1_000_000.0 / resolution
+    }
+
+    fn send(&mut self, message: &OutputMessage) {
+        if let OutputMessage::Channel(MidiChannelMessage::ChannelVoiceMessage(cvm)) = message {
+            match *cvm {
+                ChannelVoiceMessage::NoteOn{channel, key, vel} if vel > 0 => self.sounding.push((channel, key)),
+                ChannelVoiceMessage::NoteOn{channel, key, ..} | ChannelVoiceMessage::NoteOff{channel, key, ..} => {
+                    match self.sounding.iter().position(|n| *n == (channel, key)) {
+                        Some(i) => {
+                            self.sounding.remove(i);
+                        },
+                        // Already ended by seeking or pausing
+                        None => return,
+                    }
+                },
+                _ => {}
+            }
+        }
+        if let OutputMessage::Channel(message) = message {
+            self.sent.apply(&MidiEvent::MidiChannelMessage(message.clone()));
+        }
+        self.output.send(message);
+    }
+
+    /// Ends every sounding note, and sends All Notes Off to every channel the song uses if all is true
+    fn silence(&mut self, all: bool) {
+        for (channel, key) in std::mem::take(&mut self.sounding) {
+            self.output.send(&OutputMessage::Channel(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::NoteOff{channel, key, vel: 0})));
+        }
+        if all {
+            let channels = self.channels;
+            for channel in (0..16u8).filter(|ch| channels[*ch as usize]) {
+                self.output.send(&OutputMessage::Channel(MidiChannelMessage::ChannelModeMessage(ChannelModeMessage::AllNotesOff{channel})));
+            }
+        }
+    }
+
+    /// Moves to the song time and restores the state of every channel the song uses.
+    /// Values sent earlier but not set at the time are returned to their defaults.
+    fn locate(&mut self, seconds: f64) {
+        self.silence(false);
+        self.next = self.messages.partition_point(|m| m.seconds < seconds);
+        let tick = self.messages.get(self.next).map_or_else(|| self.smf.end_tick(), |m| m.tick);
+        let state = self.smf.state_before(tick);
+        let channels = self.channels;
+        for channel in (0..16u8).filter(|ch| channels[*ch as usize]) {
+            self.output.send(&OutputMessage::Channel(MidiChannelMessage::ChannelModeMessage(ChannelModeMessage::ResetAllControllers{channel})));
+            for message in state.channel(channel).restore_messages_from(self.sent.channel(channel), channel) {
+                self.output.send(&OutputMessage::Channel(message));
+            }
+        }
+        self.sent = state;
+
+        // First pulse on a sixteenth note at or after the time, so that it can be given by Song Position Pointer
+        let per_pulse = self.tempo_map.resolution() as f64 / CLOCKS_PER_QUARTER as f64;
+        let mut pulse = (self.tempo_map.seconds_to_tick(seconds) as f64 / per_pulse).floor() as u64;
+        while pulse > 0 && self.pulse_seconds(pulse - 1) >= seconds {
+            pulse -= 1;
+        }
+        while self.pulse_seconds(pulse) < seconds {
+            pulse += 1;
+        }
+        self.next_pulse = pulse.div_ceil(CLOCKS_PER_SIXTEENTH) * CLOCKS_PER_SIXTEENTH;
+
+        if self.send_clock && self.status == PlaybackStatus::Playing {
+            self.send_transport_stop();
+            self.send_song_position();
+            self.output.send(&OutputMessage::RealTime(SystemRealTimeMessage::Continue));
+        }
+
+        self.position = seconds;
+        self.anchor = self.clock.now();
+    }
+}
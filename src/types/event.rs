@@ -1,8 +1,9 @@
 
 use super::message;
 use super::message::SmfElement;
+use crate::file::filerw::SmfError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MidiEvent {
     MidiChannelMessage(message::MidiChannelMessage),
     MetaEvent(message::MetaEvent),
@@ -10,13 +11,19 @@ pub enum MidiEvent {
 }
 
 /// Represents a delta_time-event pair in SMF
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EventPair {
     time: u32, // delta_time (tick)
     event: MidiEvent,
     absolute_tick: Option<u32>
 }
 
+impl MidiEvent {
+    pub fn is_end_of_track(&self) -> bool {
+        matches!(self, MidiEvent::MetaEvent(message::MetaEvent::EndOfTrack))
+    }
+}
+
 impl SmfElement for MidiEvent {
     fn raw(&self) -> std::vec::Vec<u8> {
         use MidiEvent::*;
@@ -39,6 +46,10 @@ impl SmfElement for EventPair {
 }
 
 impl EventPair {
+    pub fn is_end_of_track(&self) -> bool {
+        self.event.is_end_of_track()
+    }
+
     pub fn new(time: u32, event: MidiEvent) -> EventPair {
         EventPair {time, event, absolute_tick: None}
     }
@@ -50,17 +61,47 @@ impl EventPair {
     pub fn event(&self) -> &MidiEvent {
         &self.event
     }
+
+    pub fn event_mut(&mut self) -> &mut MidiEvent {
+        &mut self.event
+    }
+
+    /// Replaces the event and returns the old one. Timing is kept as is.
+    pub fn set_event(&mut self, event: MidiEvent) -> MidiEvent {
+        std::mem::replace(&mut self.event, event)
+    }
+
+    /// Returns delta time (tick)
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    /// Sets delta time without touching the following events.
+    /// Use TrackChunk methods if absolute positions of the other events must be kept.
+    pub fn set_time(&mut self, time: u32) {
+        self.time = time;
+        self.absolute_tick = None;
+    }
+
+    /// Returns absolute tick computed by TrackChunk::compute_absolute_tick
+    pub fn absolute_tick(&self) -> Option<u32> {
+        self.absolute_tick
+    }
+
+    pub fn into_event(self) -> MidiEvent {
+        self.event
+    }
 }
 
 // MThd and MTrk //
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MidiChunk {
     HeaderChunk(HeaderChunk),
     TrackChunk(TrackChunk)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct HeaderChunk {
     pub length: u32, // should always be 0x00000006
     pub format: u16,
@@ -68,7 +109,7 @@ pub struct HeaderChunk {
     pub resolution: u16
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TrackChunk {
     pub length: u32,
     pub events: std::vec::Vec<EventPair>,
@@ -82,7 +123,7 @@ pub struct TrackChunkIter<'a> {
 impl SmfElement for HeaderChunk {
     fn raw(&self) -> std::vec::Vec<u8> {
         vec![
-            'M' as u8, 'T' as u8, 'h' as u8, 'd' as u8,
+            b'M', b'T', b'h', b'd',
             ((self.length & (0xFF << 24)) >> 24) as u8, ((self.length & (0xFF << 16)) >> 16) as u8 , ((self.length & (0xFF << 8)) >> 8) as u8, (self.length & 0xFF) as u8,
             ((self.format & (0xFF << 8)) >> 8) as u8, (self.format & 0xFF) as u8,
             ((self.tracks & (0xFF << 8)) >> 8) as u8, (self.tracks & 0xFF) as u8,
@@ -94,7 +135,7 @@ impl SmfElement for HeaderChunk {
 impl SmfElement for TrackChunk {
     fn raw(&self) -> std::vec::Vec<u8> {
         let mut binary = vec![
-            'M' as u8, 'T' as u8, 'r' as u8, 'k' as u8,
+            b'M', b'T', b'r', b'k',
             ((self.length & (0xFF << 24)) >> 24) as u8, ((self.length & (0xFF << 16)) >> 16) as u8 , ((self.length & (0xFF << 8)) >> 8) as u8, (self.length & 0xFF) as u8,
         ];
         for pair in &self.events {
@@ -136,17 +177,181 @@ impl<'a> ExactSizeIterator for TrackChunkIter<'a> {
 }
 
 impl TrackChunk {
+    /// Creates a track from delta-time event pairs and calculates its length
+    pub fn new(events: std::vec::Vec<EventPair>) -> TrackChunk {
+        let mut track = TrackChunk {length: 0, events};
+        track.recalculate_length();
+        track
+    }
+
+    /// Creates a track from (absolute tick, event) pairs.
+    /// Events are stably sorted by tick, so events on the same tick keep their order.
+    pub fn from_absolute(events: std::vec::Vec<(u32, MidiEvent)>) -> TrackChunk {
+        let mut events = events;
+        events.sort_by_key(|(tick, _)| *tick);
+
+        let mut prev = 0;
+        let pairs = events.into_iter().map(|(tick, event)| {
+            let pair = EventPair {time: tick - prev, event, absolute_tick: Some(tick)};
+            prev = tick;
+            pair
+        }).collect();
+        TrackChunk::new(pairs)
+    }
+
+    /// Returns (absolute tick, event) pairs of this track
+    pub fn to_absolute(&self) -> std::vec::Vec<(u32, MidiEvent)> {
+        self.absolute_ticks().into_iter()
+            .zip(self.events.iter().map(|pair| pair.event.clone()))
+            .collect()
+    }
+
+    /// Returns absolute tick of every event
+    pub fn absolute_ticks(&self) -> std::vec::Vec<u32> {
+        let mut tick = 0;
+        self.events.iter().map(|pair| { tick += pair.time; tick }).collect()
+    }
+
     pub fn len(&self) -> usize {
         self.events.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&EventPair> {
+        self.events.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut EventPair> {
+        self.events.get_mut(index)
+    }
+
+    /// Returns the text of the first SequenceTrackName meta event
+    pub fn name(&self) -> Option<String> {
+        self.events.iter().find_map(|pair| match &pair.event {
+            MidiEvent::MetaEvent(message::MetaEvent::SequenceTrackName{text, ..}) => Some(String::from_utf8_lossy(text).into_owned()),
+            _ => None
+        })
+    }
+
+    /// Returns absolute tick of the last event (the track end)
+    pub fn end_tick(&self) -> u32 {
+        self.events.iter().fold(0, |acc, pair| acc + pair.time)
+    }
+
+    /// Inserts an event at the absolute tick and returns its index.
+    /// The event is placed after the events on the same tick but never after a trailing EndOfTrack,
+    /// which is moved to the inserted tick if necessary.
+    /// Delta time of the following event is adjusted so that the other events keep their positions.
+    pub fn insert_event(&mut self, tick: u32, event: MidiEvent) -> usize {
+        let ticks = self.absolute_ticks();
+        let mut limit = self.events.len();
+        if let Some(last) = self.events.last() {
+            if last.is_end_of_track() {
+                limit -= 1;
+            }
+        }
+
+        let index = ticks[..limit].iter().position(|t| *t > tick).unwrap_or(limit);
+        let prev = if index == 0 { 0 } else { ticks[index - 1] };
+        if let Some(next) = self.events.get_mut(index) {
+            next.time = ticks[index].saturating_sub(tick);
+            next.absolute_tick = None;
+        }
+        self.events.insert(index, EventPair::new(tick - prev, event));
+        self.recalculate_length();
+        index
+    }
+
+    /// Removes the event at the index.
+    /// Its delta time is added to the following event so that the other events keep their positions.
+    pub fn remove_event(&mut self, index: usize) -> EventPair {
+        let removed = self.events.remove(index);
+        if let Some(next) = self.events.get_mut(index) {
+            next.time += removed.time;
+        }
+        self.recalculate_length();
+        removed
+    }
+
+    /// Replaces the event at the index keeping its timing, and returns the old event
+    pub fn replace_event(&mut self, index: usize, event: MidiEvent) -> MidiEvent {
+        let old = self.events[index].set_event(event);
+        self.recalculate_length();
+        old
+    }
+
+    /// Moves the event at the index to the absolute tick and returns its new index
+    pub fn move_event(&mut self, index: usize, tick: u32) -> usize {
+        let removed = self.remove_event(index);
+        self.insert_event(tick, removed.event)
+    }
+
+    /// Removes every event for which the predicate returns false, keeping the positions of the rest
+    pub fn retain_events<F>(&mut self, mut f: F) where F: FnMut(&MidiEvent) -> bool {
+        let mut carry = 0;
+        let mut kept = std::vec::Vec::with_capacity(self.events.len());
+        for mut pair in self.events.drain(..) {
+            if f(&pair.event) {
+                pair.time += carry;
+                carry = 0;
+                kept.push(pair);
+            } else {
+                carry += pair.time;
+            }
+        }
+        self.events = kept;
+        self.recalculate_length();
+    }
+
+    /// Returns binary expression of the track omitting status bytes repeated by running status.
+    /// The length field is computed from the encoded events.
+    pub fn raw_with_running_status(&self) -> std::vec::Vec<u8> {
+        let mut body = std::vec::Vec::new();
+        let mut running_status: Option<u8> = None;
+        for pair in &self.events {
+            body.extend(message::to_vlq(pair.time));
+            let raw = pair.event.raw();
+            match &pair.event {
+                MidiEvent::MidiChannelMessage(_) => {
+                    if running_status == Some(raw[0]) {
+                        body.extend(&raw[1..]);
+                    } else {
+                        running_status = Some(raw[0]);
+                        body.extend(raw);
+                    }
+                },
+                _ => {
+                    // Meta and SysEx events cancel running status
+                    running_status = None;
+                    body.extend(raw);
+                }
+            }
+        }
+
+        let length = body.len() as u32;
+        let mut binary = vec![
+            b'M', b'T', b'r', b'k',
+            ((length & (0xFF << 24)) >> 24) as u8, ((length & (0xFF << 16)) >> 16) as u8 , ((length & (0xFF << 8)) >> 8) as u8, (length & 0xFF) as u8,
+        ];
+        binary.extend(body);
+        binary
+    }
+
     /// Update length field by calculating the sum of event length
     pub fn recalculate_length(&mut self) {
         self.length = self.events.iter().fold(0, |acc, event| { acc + (event.raw().len() as u32)});
     }
 
+    /// Fills EventPair::absolute_tick of every event
     pub fn compute_absolute_tick(&mut self) {
-        unimplemented!()
+        let mut tick = 0;
+        for pair in &mut self.events {
+            tick += pair.time;
+            pair.absolute_tick = Some(tick);
+        }
     }
 }
 
@@ -162,7 +367,7 @@ impl SmfElement for MidiChunk {
 
 // MidiFile //
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SMF {
     header: HeaderChunk,
     tracks: Vec<TrackChunk>
@@ -185,6 +390,81 @@ impl SMF {
         SMF { header, tracks, }
     }
 
+    pub fn header(&self) -> &HeaderChunk {
+        &self.header
+    }
+
+    pub fn format(&self) -> u16 {
+        self.header.format
+    }
+
+    pub fn set_format(&mut self, format: u16) {
+        self.header.format = format;
+    }
+
+    /// Returns ticks per quarter note
+    pub fn resolution(&self) -> u16 {
+        self.header.resolution
+    }
+
+    /// Sets ticks per quarter note. Delta times are NOT rescaled.
+    pub fn set_resolution(&mut self, resolution: u16) {
+        self.header.resolution = resolution;
+    }
+
+    pub fn tracks(&self) -> &[TrackChunk] {
+        &self.tracks
+    }
+
+    /// Tracks can be edited but not added or removed through the slice.
+    /// Use add_track, insert_track and remove_track to keep HeaderChunk::tracks consistent.
+    pub fn tracks_mut(&mut self) -> &mut [TrackChunk] {
+        &mut self.tracks
+    }
+
+    pub fn track(&self, index: usize) -> Option<&TrackChunk> {
+        self.tracks.get(index)
+    }
+
+    pub fn track_mut(&mut self, index: usize) -> Option<&mut TrackChunk> {
+        self.tracks.get_mut(index)
+    }
+
+    /// Appends a track. Fails if the file would have more tracks than the header can count.
+    pub fn add_track(&mut self, track: TrackChunk) -> Result<(), SmfError> {
+        self.insert_track(self.tracks.len(), track)
+    }
+
+    /// Inserts a track. Fails if the file would have more tracks than the header can count.
+    pub fn insert_track(&mut self, index: usize, track: TrackChunk) -> Result<(), SmfError> {
+        if self.tracks.len() >= u16::MAX as usize {
+            return Err(SmfError::new("too many tracks"));
+        }
+        self.tracks.insert(index, track);
+        self.header.tracks = self.tracks.len() as u16;
+        Ok(())
+    }
+
+    pub fn remove_track(&mut self, index: usize) -> TrackChunk {
+        let removed = self.tracks.remove(index);
+        self.header.tracks = self.tracks.len() as u16;
+        removed
+    }
+
+    /// Replaces all tracks. Fails if there are more tracks than the header can count.
+    pub fn set_tracks(&mut self, tracks: Vec<TrackChunk>) -> Result<(), SmfError> {
+        if tracks.len() > u16::MAX as usize {
+            return Err(SmfError::new("too many tracks"));
+        }
+        self.tracks = tracks;
+        self.header.tracks = self.tracks.len() as u16;
+        Ok(())
+    }
+
+    pub fn into_tracks(self) -> Vec<TrackChunk> {
+        self.tracks
+    }
+
     /// Only works when time is absolute.
     pub fn merge_tracks(&mut self) {
         let mut merged: Vec<EventPair> = Vec::new();
@@ -192,8 +472,8 @@ impl SMF {
         let num_events: Vec<usize> = self.tracks.iter().map(|i| i.len()).collect();
         let mut indices: Vec<usize> = vec![0; self.tracks.len()];
         loop {
-            let mut min_time = std::u32::MAX;
-            let mut min_index = std::usize::MAX;
+            let mut min_time = u32::MAX;
+            let mut min_index = usize::MAX;
             for (i, tracks) in self.tracks.iter().enumerate() {
                 if indices[i] >= num_events[i] {
                     continue;
@@ -206,7 +486,7 @@ impl SMF {
                 }
             }
 
-            if min_index == std::usize::MAX {
+            if min_index == usize::MAX {
                 break;
             }
 
@@ -222,6 +502,18 @@ impl SMF {
             length: sum_length,
             events: merged,
         }];
+        self.header.tracks = 1;
+    }
+
+    /// Returns binary expression of the file using running status in every track
+    pub fn raw_with_running_status(&self) -> Vec<u8> {
+        let mut raw_all = self.header.raw();
+
+        for track in &self.tracks {
+            raw_all.extend(track.raw_with_running_status());
+        }
+
+        raw_all
     }
 
     pub fn recalculate_length(&mut self) {
@@ -231,6 +523,8 @@ impl SMF {
     }
 
     pub fn compute_absolute_tick(&mut self) {
-        unimplemented!()
+        for track in &mut self.tracks {
+            track.compute_absolute_tick();
+        }
     }
 }
\ No newline at end of file
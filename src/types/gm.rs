@@ -0,0 +1,578 @@
+/// General MIDI Level 1 instrument names indexed by program number
+pub const GM_INSTRUMENTS: [&str; 128] = [
+    // Piano
+    "Acoustic Grand Piano", "Bright Acoustic Piano", "Electric Grand Piano", "Honky-tonk Piano",
+    "Electric Piano 1", "Electric Piano 2", "Harpsichord", "Clavi",
+    // Chromatic Percussion
+    "Celesta", "Glockenspiel", "Music Box", "Vibraphone",
+    "Marimba", "Xylophone", "Tubular Bells", "Dulcimer",
+    // Organ
+    "Drawbar Organ", "Percussive Organ", "Rock Organ", "Church Organ",
+    "Reed Organ", "Accordion", "Harmonica", "Tango Accordion",
+    // Guitar
+    "Acoustic Guitar (nylon)", "Acoustic Guitar (steel)", "Electric Guitar (jazz)", "Electric Guitar (clean)",
+    "Electric Guitar (muted)", "Overdriven Guitar", "Distortion Guitar", "Guitar harmonics",
+    // Bass
+    "Acoustic Bass", "Electric Bass (finger)", "Electric Bass (pick)", "Fretless Bass",
+    "Slap Bass 1", "Slap Bass 2", "Synth Bass 1", "Synth Bass 2",
+    // Strings
+    "Violin", "Viola", "Cello", "Contrabass",
+    "Tremolo Strings", "Pizzicato Strings", "Orchestral Harp", "Timpani",
+    // Ensemble
+    "String Ensemble 1", "String Ensemble 2", "SynthStrings 1", "SynthStrings 2",
+    "Choir Aahs", "Voice Oohs", "Synth Voice", "Orchestra Hit",
+    // Brass
+    "Trumpet", "Trombone", "Tuba", "Muted Trumpet",
+    "French Horn", "Brass Section", "SynthBrass 1", "SynthBrass 2",
+    // Reed
+    "Soprano Sax", "Alto Sax", "Tenor Sax", "Baritone Sax",
+    "Oboe", "English Horn", "Bassoon", "Clarinet",
+    // Pipe
+    "Piccolo", "Flute", "Recorder", "Pan Flute",
+    "Blown Bottle", "Shakuhachi", "Whistle", "Ocarina",
+    // Synth Lead
+    "Lead 1 (square)", "Lead 2 (sawtooth)", "Lead 3 (calliope)", "Lead 4 (chiff)",
+    "Lead 5 (charang)", "Lead 6 (voice)", "Lead 7 (fifths)", "Lead 8 (bass + lead)",
+    // Synth Pad
+    "Pad 1 (new age)", "Pad 2 (warm)", "Pad 3 (polysynth)", "Pad 4 (choir)",
+    "Pad 5 (bowed)", "Pad 6 (metallic)", "Pad 7 (halo)", "Pad 8 (sweep)",
+    // Synth Effects
+    "FX 1 (rain)", "FX 2 (soundtrack)", "FX 3 (crystal)", "FX 4 (atmosphere)",
+    "FX 5 (brightness)", "FX 6 (goblins)", "FX 7 (echoes)", "FX 8 (sci-fi)",
+    // Ethnic
+    "Sitar", "Banjo", "Shamisen", "Koto",
+    "Kalimba", "Bag pipe", "Fiddle", "Shanai",
+    // Percussive
+    "Tinkle Bell", "Agogo", "Steel Drums", "Woodblock",
+    "Taiko Drum", "Melodic Tom", "Synth Drum", "Reverse Cymbal",
+    // Sound Effects
+    "Guitar Fret Noise", "Breath Noise", "Seashore", "Bird Tweet",
+    "Telephone Ring", "Helicopter", "Applause", "Gunshot",
+];
+
+/// Returns the GM instrument name of the program number (0-127)
+pub fn instrument_name(program: u8) -> &'static str {
+    GM_INSTRUMENTS[(program & 0x7F) as usize]
+}
+
+/// Channel number of GM percussion (channel 10)
+pub const DRUM_CHANNEL: u8 = 9;
+
+/// GM instrument family names. Each family covers 8 programs.
+pub const GM_FAMILIES: [&str; 16] = [
+    "Piano", "Chromatic Percussion", "Organ", "Guitar",
+    "Bass", "Strings", "Ensemble", "Brass",
+    "Reed", "Pipe", "Synth Lead", "Synth Pad",
+    "Synth Effects", "Ethnic", "Percussive", "Sound Effects",
+];
+
+/// Returns the GM family name of the program number
+pub fn family_name(program: u8) -> &'static str {
+    GM_FAMILIES[((program & 0x7F) / 8) as usize]
+}
+
+/// First key of GM1_PERCUSSION
+pub const GM1_PERCUSSION_FIRST_KEY: u8 = 35;
+
+/// GM1 percussion names of keys 35-81 on channel 10
+pub const GM1_PERCUSSION: [&str; 47] = [
+    "Acoustic Bass Drum", "Bass Drum 1", "Side Stick", "Acoustic Snare",
+    "Hand Clap", "Electric Snare", "Low Floor Tom", "Closed Hi Hat",
+    "High Floor Tom", "Pedal Hi-Hat", "Low Tom", "Open Hi-Hat",
+    "Low-Mid Tom", "Hi-Mid Tom", "Crash Cymbal 1", "High Tom",
+    "Ride Cymbal 1", "Chinese Cymbal", "Ride Bell", "Tambourine",
+    "Splash Cymbal", "Cowbell", "Crash Cymbal 2", "Vibraslap",
+    "Ride Cymbal 2", "Hi Bongo", "Low Bongo", "Mute Hi Conga",
+    "Open Hi Conga", "Low Conga", "High Timbale", "Low Timbale",
+    "High Agogo", "Low Agogo", "Cabasa", "Maracas",
+    "Short Whistle", "Long Whistle", "Short Guiro", "Long Guiro",
+    "Claves", "Hi Wood Block", "Low Wood Block", "Mute Cuica",
+    "Open Cuica", "Mute Triangle", "Open Triangle",
+];
+
+/// Percussion added by GM2 on keys 27-34 and 82-87
+const GM2_PERCUSSION_LOW: [&str; 8] = [
+    "High Q", "Slap", "Scratch Push", "Scratch Pull",
+    "Sticks", "Square Click", "Metronome Click", "Metronome Bell",
+];
+const GM2_PERCUSSION_HIGH: [&str; 6] = [
+    "Shaker", "Jingle Bell", "Belltree", "Castanets", "Mute Surdo", "Open Surdo",
+];
+
+/// Returns the GM1 percussion name of the key on channel 10
+pub fn gm1_percussion_name(key: u8) -> Option<&'static str> {
+    key.checked_sub(GM1_PERCUSSION_FIRST_KEY).and_then(|i| GM1_PERCUSSION.get(i as usize)).copied()
+}
+
+/// Returns the GM2 percussion name of the key, which extends GM1 below and above its range
+pub fn percussion_name(key: u8) -> Option<&'static str> {
+    match key {
+        27..=34 => Some(GM2_PERCUSSION_LOW[(key - 27) as usize]),
+        82..=87 => Some(GM2_PERCUSSION_HIGH[(key - 82) as usize]),
+        _ => gm1_percussion_name(key)
+    }
+}
+
+/// Returns the name of the controller number as defined by the MIDI 1.0 specification
+pub fn controller_name(cc: u8) -> Option<&'static str> {
+    let name = match cc {
+        0 => "Bank Select",
+        1 => "Modulation Wheel",
+        2 => "Breath Controller",
+        4 => "Foot Controller",
+        5 => "Portamento Time",
+        6 => "Data Entry MSB",
+        7 => "Channel Volume",
+        8 => "Balance",
+        10 => "Pan",
+        11 => "Expression Controller",
+        12 => "Effect Control 1",
+        13 => "Effect Control 2",
+        16 => "General Purpose Controller 1",
+        17 => "General Purpose Controller 2",
+        18 => "General Purpose Controller 3",
+        19 => "General Purpose Controller 4",
+        32 => "Bank Select LSB",
+        33 => "Modulation Wheel LSB",
+        34 => "Breath Controller LSB",
+        36 => "Foot Controller LSB",
+        37 => "Portamento Time LSB",
+        38 => "Data Entry LSB",
+        39 => "Channel Volume LSB",
+        40 => "Balance LSB",
+        42 => "Pan LSB",
+        43 => "Expression Controller LSB",
+        44 => "Effect Control 1 LSB",
+        45 => "Effect Control 2 LSB",
+        48 => "General Purpose Controller 1 LSB",
+        49 => "General Purpose Controller 2 LSB",
+        50 => "General Purpose Controller 3 LSB",
+        51 => "General Purpose Controller 4 LSB",
+        64 => "Damper Pedal (Sustain)",
+        65 => "Portamento On/Off",
+        66 => "Sostenuto",
+        67 => "Soft Pedal",
+        68 => "Legato Footswitch",
+        69 => "Hold 2",
+        70 => "Sound Variation",
+        71 => "Timbre/Harmonic Intensity",
+        72 => "Release Time",
+        73 => "Attack Time",
+        74 => "Brightness",
+        75 => "Decay Time",
+        76 => "Vibrato Rate",
+        77 => "Vibrato Depth",
+        78 => "Vibrato Delay",
+        79 => "Sound Controller 10",
+        80 => "General Purpose Controller 5",
+        81 => "General Purpose Controller 6",
+        82 => "General Purpose Controller 7",
+        83 => "General Purpose Controller 8",
+        84 => "Portamento Control",
+        88 => "High Resolution Velocity Prefix",
+        91 => "Reverb Send Level",
+        92 => "Tremolo Depth",
+        93 => "Chorus Send Level",
+        94 => "Celeste (Detune) Depth",
+        95 => "Phaser Depth",
+        96 => "Data Increment",
+        97 => "Data Decrement",
+        98 => "NRPN LSB",
+        99 => "NRPN MSB",
+        100 => "RPN LSB",
+        101 => "RPN MSB",
+        120 => "All Sound Off",
+        121 => "Reset All Controllers",
+        122 => "Local Control",
+        123 => "All Notes Off",
+        124 => "Omni Mode Off",
+        125 => "Omni Mode On",
+        126 => "Mono Mode On",
+        127 => "Poly Mode On",
+        _ => return None
+    };
+    Some(name)
+}
+
+/// Returns the controller name, or "Controller n" for undefined numbers
+pub fn controller_description(cc: u8) -> String {
+    controller_name(cc).map_or_else(|| format!("Controller {}", cc), |name| name.to_string())
+}
+
+/// GM2 melodic variations selected by bank LSB (bank MSB 121), as listed in the GM2 sound set.
+/// With the 128 GM1 sounds at LSB 0 they make up the 256 GM2 melodic sounds.
+pub const GM2_VARIATIONS: [(u8, u8, &str); 128] = [
+    (0, 1, "Wide Acoustic Grand"), (0, 2, "Dark Acoustic Grand"),
+    (1, 1, "Wide Bright Acoustic"),
+    (2, 1, "Wide Electric Grand"),
+    (3, 1, "Wide Honky-tonk"),
+    (4, 1, "Detuned Electric Piano 1"), (4, 2, "Electric Piano 1 Variation"), (4, 3, "60's Electric Piano"),
+    (5, 1, "Detuned Electric Piano 2"), (5, 2, "Electric Piano 2 Variation"), (5, 3, "Legend Electric Piano"), (5, 4, "Phase Electric Piano"),
+    (6, 1, "Coupled Harpsichord"), (6, 2, "Wide Harpsichord"), (6, 3, "Open Harpsichord"),
+    (7, 1, "Pulse Clavinet"),
+    (11, 1, "Wet Vibraphone"),
+    (12, 1, "Wide Marimba"),
+    (14, 1, "Church Bells"), (14, 2, "Carillon"),
+    (16, 1, "Detuned Drawbar Organ"), (16, 2, "Italian 60's Organ"), (16, 3, "Drawbar Organ 2"),
+    (17, 1, "Detuned Percussive Organ"), (17, 2, "Percussive Organ 2"),
+    (19, 1, "Church Organ (Octave Mix)"), (19, 2, "Detuned Church Organ"),
+    (20, 1, "Puff Organ"),
+    (21, 1, "Italian Accordion"),
+    (24, 1, "Ukulele"), (24, 2, "Open Nylon Guitar"), (24, 3, "Nylon Guitar 2"),
+    (25, 1, "12-String Guitar"), (25, 2, "Mandolin"), (25, 3, "Steel Guitar with Body Sound"),
+    (26, 1, "Pedal Steel Guitar"),
+    (27, 1, "Detuned Clean Electric Guitar"), (27, 2, "Mid Tone Guitar"),
+    (28, 1, "Funky Cutting Electric Guitar"), (28, 2, "Muted Velo-Sw Electric Guitar"), (28, 3, "Jazz Man"),
+    (29, 1, "Guitar Pinch"),
+    (30, 1, "Feedback Guitar"), (30, 2, "Distortion Rhythm Guitar"),
+    (31, 1, "Guitar Feedback"),
+    (33, 1, "Finger Slap Bass"),
+    (38, 1, "Synth Bass 101"), (38, 2, "Synth Bass 3 (Resonance)"), (38, 3, "Clavi Bass"), (38, 4, "Hammer"),
+    (39, 1, "Synth Bass 4 (Attack)"), (39, 2, "Synth Bass (Rubber)"), (39, 3, "Attack Pulse"),
+    (40, 1, "Slow Violin"),
+    (46, 1, "Yang Qin"),
+    (48, 1, "Strings and Brass"), (48, 2, "60's Strings"),
+    (50, 1, "Synth Strings 3"),
+    (52, 1, "Choir Aahs 2"),
+    (53, 1, "Humming"),
+    (54, 1, "Analog Voice"),
+    (55, 1, "Bass Hit Plus"), (55, 2, "6th Hit"), (55, 3, "Euro Hit"),
+    (56, 1, "Dark Trumpet Soft"),
+    (57, 1, "Trombone 2"), (57, 2, "Bright Trombone"),
+    (59, 1, "Muted Trumpet 2"),
+    (60, 1, "French Horn 2 (Warm)"),
+    (61, 1, "Brass Section 2 (Octave Mix)"),
+    (62, 1, "Synth Brass 3"), (62, 2, "Analog Synth Brass 1"), (62, 3, "Jump Brass"),
+    (63, 1, "Synth Brass 4"), (63, 2, "Analog Synth Brass 2"),
+    (80, 1, "Square Lead 2"), (80, 2, "Sine Wave"),
+    (81, 1, "Saw Lead 2"), (81, 2, "Doctor Solo"), (81, 3, "Natural Lead"), (81, 4, "Sequenced Saw"),
+    (84, 1, "Wire Lead"),
+    (87, 1, "Delayed Lead"),
+    (89, 1, "Sine Pad"),
+    (91, 1, "Itopia"),
+    (98, 1, "Synth Mallet"),
+    (102, 1, "Echo Bell"), (102, 2, "Echo Pan"),
+    (104, 1, "Sitar 2 (Bend)"),
+    (107, 1, "Taisho Koto"),
+    (115, 1, "Castanets"),
+    (116, 1, "Concert Bass Drum"),
+    (117, 1, "Melodic Tom 2 (Power)"),
+    (118, 1, "Rhythm Box Tom"), (118, 2, "Electric Drum"),
+    (120, 1, "Guitar Cutting Noise"), (120, 2, "Acoustic Bass String Slap"),
+    (121, 1, "Flute Key Click"),
+    (122, 1, "Rain"), (122, 2, "Thunder"), (122, 3, "Wind"), (122, 4, "Stream"), (122, 5, "Bubble"),
+    (123, 1, "Dog"), (123, 2, "Horse Gallop"), (123, 3, "Bird Tweet 2"),
+    (124, 1, "Telephone Ring 2"), (124, 2, "Door Creaking"), (124, 3, "Door"), (124, 4, "Scratch"), (124, 5, "Wind Chime"),
+    (125, 1, "Car Engine"), (125, 2, "Car Stop"), (125, 3, "Car Pass"), (125, 4, "Car Crash"), (125, 5, "Siren"), (125, 6, "Train"), (125, 7, "Jetplane"), (125, 8, "Starship"), (125, 9, "Burst Noise"),
+    (126, 1, "Laughing"), (126, 2, "Screaming"), (126, 3, "Punch"), (126, 4, "Heart Beat"), (126, 5, "Footsteps"),
+    (127, 1, "Machine Gun"), (127, 2, "Lasergun"), (127, 3, "Explosion"),
+];
+
+/// GS variation tones selected by bank MSB (Roland SC-55 map)
+const GS_VARIATIONS: [(u8, u8, &str); 96] = [
+    (0, 8, "Piano 1w"), (0, 16, "Piano 1d"),
+    (1, 8, "Piano 2w"),
+    (2, 8, "Piano 3w"),
+    (3, 8, "Honky-tonk w"),
+    (4, 8, "Detuned EP 1"), (4, 16, "E.Piano 1w"), (4, 24, "60's E.Piano"),
+    (5, 8, "Detuned EP 2"), (5, 16, "E.Piano 2w"),
+    (6, 8, "Coupled Hps."), (6, 16, "Harpsi.w"), (6, 24, "Harpsi.o"),
+    (11, 8, "Vib.w"),
+    (12, 8, "Marimba w"),
+    (14, 8, "Church Bell"), (14, 9, "Carillon"),
+    (16, 8, "Detuned Or.1"), (16, 16, "60's Organ 1"), (16, 32, "Organ 4"),
+    (17, 8, "Detuned Or.2"), (17, 32, "Organ 5"),
+    (19, 8, "Church Org.2"), (19, 16, "Church Org.3"),
+    (21, 8, "Accordion It"),
+    (24, 8, "Ukulele"), (24, 16, "Nylon Gt.o"), (24, 32, "Nylon Gt.2"),
+    (25, 8, "12-str.Gt"), (25, 16, "Mandolin"),
+    (26, 8, "Hawaiian Gt."),
+    (27, 8, "Chorus Gt."),
+    (28, 8, "Funk Gt."), (28, 16, "Funk Gt.2"),
+    (30, 8, "Feedback Gt."),
+    (31, 8, "Gt. Feedback"),
+    (38, 1, "SynthBass101"), (38, 8, "Synth Bass 3"),
+    (39, 8, "Synth Bass 4"), (39, 16, "Rubber Bass"),
+    (40, 8, "Slow Violin"),
+    (48, 8, "Orchestra"),
+    (50, 8, "Syn.Strings3"),
+    (52, 32, "Choir Aahs 2"),
+    (60, 8, "Fr.Horn 2"),
+    (61, 8, "Brass 2"),
+    (62, 8, "Synth Brass3"), (62, 16, "AnalogBrass1"),
+    (63, 8, "Synth Brass4"), (63, 16, "AnalogBrass2"),
+    (80, 1, "Square"), (80, 8, "Sine Wave"),
+    (81, 1, "Saw"), (81, 8, "Doctor Solo"),
+    (98, 1, "Syn Mallet"),
+    (102, 1, "Echo Bell"), (102, 2, "Echo Pan"),
+    (107, 8, "Taisho Koto"),
+    (115, 8, "Castanets"),
+    (116, 8, "Concert BD"),
+    (117, 8, "Melo. Tom 2"),
+    (118, 8, "808 Tom"), (118, 9, "Elec Perc."),
+    (120, 1, "Gt.Cut Noise"), (120, 2, "String Slap"),
+    (121, 1, "Fl.Key Click"),
+    (122, 1, "Rain"), (122, 2, "Thunder"), (122, 3, "Wind"), (122, 4, "Stream"), (122, 5, "Bubble"),
+    (123, 1, "Dog"), (123, 2, "Horse-Gallop"), (123, 3, "Bird 2"),
+    (124, 1, "Telephone 2"), (124, 2, "DoorCreaking"), (124, 3, "Door"), (124, 4, "Scratch"), (124, 5, "Windchime"),
+    (125, 1, "Car-Engine"), (125, 2, "Car-Stop"), (125, 3, "Car-Pass"), (125, 4, "Car-Crash"), (125, 5, "Siren"), (125, 6, "Train"), (125, 7, "Jetplane"), (125, 8, "Starship"), (125, 9, "Burst Noise"),
+    (126, 1, "Laughing"), (126, 2, "Screaming"), (126, 3, "Punch"), (126, 4, "Heart Beat"), (126, 5, "Footsteps"),
+    (127, 1, "Machine Gun"), (127, 2, "Lasergun"), (127, 3, "Explosion"),
+];
+
+/// MT-32 compatible tones of GS bank MSB 127, indexed by program number
+const MT32_INSTRUMENTS: [&str; 128] = [
+    "Acou Piano 1", "Acou Piano 2", "Acou Piano 3", "Elec Piano 1", "Elec Piano 2", "Elec Piano 3", "Elec Piano 4", "Honkytonk",
+    "Elec Org 1", "Elec Org 2", "Elec Org 3", "Elec Org 4", "Pipe Org 1", "Pipe Org 2", "Pipe Org 3", "Accordion",
+    "Harpsi 1", "Harpsi 2", "Harpsi 3", "Clavi 1", "Clavi 2", "Clavi 3", "Celesta 1", "Celesta 2",
+    "Syn Brass 1", "Syn Brass 2", "Syn Brass 3", "Syn Brass 4", "Syn Bass 1", "Syn Bass 2", "Syn Bass 3", "Syn Bass 4",
+    "Fantasy", "Harmo Pan", "Chorale", "Glasses", "Soundtrack", "Atmosphere", "Warm Bell", "Funny Vox",
+    "Echo Bell", "Ice Rain", "Oboe 2001", "Echo Pan", "Doctor Solo", "Schooldaze", "Bellsinger", "Square Wave",
+    "Str Sect 1", "Str Sect 2", "Str Sect 3", "Pizzicato", "Violin 1", "Violin 2", "Cello 1", "Cello 2",
+    "Contrabass", "Harp 1", "Harp 2", "Guitar 1", "Guitar 2", "Elec Gtr 1", "Elec Gtr 2", "Sitar",
+    "Acou Bass 1", "Acou Bass 2", "Elec Bass 1", "Elec Bass 2", "Slap Bass 1", "Slap Bass 2", "Fretless 1", "Fretless 2",
+    "Flute 1", "Flute 2", "Piccolo 1", "Piccolo 2", "Recorder", "Pan Pipes", "Sax 1", "Sax 2",
+    "Sax 3", "Sax 4", "Clarinet 1", "Clarinet 2", "Oboe", "Engl Horn", "Bassoon", "Harmonica",
+    "Trumpet 1", "Trumpet 2", "Trombone 1", "Trombone 2", "Fr Horn 1", "Fr Horn 2", "Tuba", "Brs Sect 1",
+    "Brs Sect 2", "Vibe 1", "Vibe 2", "Syn Mallet", "Windbell", "Glock", "Tube Bell", "Xylophone",
+    "Marimba", "Koto", "Sho", "Shakuhachi", "Whistle 1", "Whistle 2", "Bottleblow", "Breathpipe",
+    "Timpani", "Melodic Tom", "Deep Snare", "Elec Perc 1", "Elec Perc 2", "Taiko", "Taiko Rim", "Cymbal",
+    "Castanets", "Triangle", "Orche Hit", "Telephone", "Bird Tweet", "One Note Jam", "Water Bell", "Jungle Tune",
+];
+
+/// XG melodic variations selected by bank LSB (bank MSB 0), as listed for XG level 1 (MU50)
+const XG_VARIATIONS: [(u8, u8, &str); 306] = [
+    (0, 1, "GrndPnoK"), (0, 18, "MelloGrP"), (0, 40, "PianoStr"), (0, 41, "Dream"),
+    (1, 1, "BritPnoK"),
+    (2, 1, "ElGrPnoK"), (2, 32, "Det.CP80"), (2, 40, "LayerCP1"), (2, 41, "LayerCP2"),
+    (3, 1, "HnkyTnkK"),
+    (4, 1, "El.Pno1K"), (4, 18, "MelloEP1"), (4, 32, "Chor.EP1"), (4, 40, "HardEl.P"), (4, 45, "VX El.P1"), (4, 64, "60sEl.P"),
+    (5, 1, "El.Pno2K"), (5, 32, "Chor.EP2"), (5, 33, "DX Hard"), (5, 34, "DXLegend"), (5, 40, "DX Phase"), (5, 41, "DX+Analg"), (5, 42, "DXKotoEP"), (5, 45, "VX El.P2"),
+    (6, 1, "Harpsi.K"), (6, 25, "Harpsi.2"), (6, 35, "Harpsi.3"),
+    (7, 1, "Clavi.K"), (7, 27, "ClaviWah"), (7, 64, "PulseClv"), (7, 65, "PierceCl"),
+    (10, 64, "Orgel"),
+    (11, 1, "VibesK"), (11, 45, "HardVibe"),
+    (12, 1, "MarimbaK"), (12, 64, "SineMrmb"), (12, 97, "Balimba"), (12, 98, "Log Drum"),
+    (14, 96, "ChrchBel"), (14, 97, "Carillon"),
+    (15, 35, "Dulcimr2"), (15, 96, "Cimbalom"), (15, 97, "Santur"),
+    (16, 32, "DetDrwOr"), (16, 33, "60sDrOr1"), (16, 34, "60sDrOr2"), (16, 35, "70sDrOr1"), (16, 36, "DrawOrg2"), (16, 37, "60sDrOr3"), (16, 38, "EvenBar"), (16, 40, "16+2\"2/3"), (16, 64, "Organ Ba"), (16, 65, "70sDrOr2"), (16, 66, "CheezOrg"), (16, 67, "DrawOrg3"),
+    (17, 24, "70sPcOr1"), (17, 32, "DetPrcOr"), (17, 33, "LiteOrg"), (17, 37, "PercOrg2"),
+    (18, 64, "RotaryOr"), (18, 65, "SloRotar"), (18, 66, "FstRotar"),
+    (19, 32, "ChurOrg3"), (19, 35, "ChurOrg2"), (19, 40, "NotreDam"), (19, 64, "OrgFlute"), (19, 65, "TrmOrgFl"),
+    (20, 40, "Puff Org"),
+    (21, 32, "Accordit"),
+    (22, 32, "Harmo 2"),
+    (23, 64, "TngoAcd2"),
+    (24, 16, "NylonGt2"), (24, 25, "NylonGt3"), (24, 43, "VelGtHrm"), (24, 96, "Ukulele"),
+    (25, 16, "SteelGt2"), (25, 35, "12StrGtr"), (25, 40, "Nyln&Stl"), (25, 41, "Stl&Body"), (25, 96, "Mandolin"),
+    (26, 18, "MelloGtr"), (26, 32, "JazzAmp"),
+    (27, 32, "ChorusGt"),
+    (28, 40, "FunkGtr1"), (28, 41, "MuteStlG"), (28, 43, "FunkGtr2"), (28, 45, "Jazz Man"),
+    (29, 43, "Gt.Pinch"),
+    (30, 40, "FeedbkGt"), (30, 41, "FeedbGt2"),
+    (31, 65, "GtFeedbk"), (31, 66, "GtrHrmo2"),
+    (32, 40, "JazzRthm"), (32, 45, "VXUprght"),
+    (33, 18, "FingrDrk"), (33, 27, "FlangeBa"), (33, 40, "Ba&DstEG"), (33, 43, "FngrSlap"), (33, 45, "FngBass2"), (33, 65, "ModAlem"),
+    (34, 28, "MutePkBa"),
+    (35, 32, "Fretles2"), (35, 33, "Fretles3"), (35, 34, "Fretles4"), (35, 96, "SynFretl"), (35, 97, "Smooth"),
+    (36, 27, "ResoSlap"), (36, 32, "PunchThm"),
+    (37, 43, "VeloSlap"),
+    (38, 18, "SynBa1Dk"), (38, 20, "FastResB"), (38, 24, "AcidBass"), (38, 35, "Clv Bass"), (38, 40, "TeknoBa"), (38, 64, "Oscar"), (38, 65, "SqrBass"), (38, 66, "RubberBa"), (38, 96, "Hammer"),
+    (39, 6, "MelloSB1"), (39, 12, "Seq Bass"), (39, 18, "ClkSynBa"), (39, 19, "SynBa2Dk"), (39, 32, "SmthBa 2"), (39, 40, "ModulrBa"), (39, 41, "DX Bass"), (39, 64, "X WireBa"),
+    (40, 8, "SlowVln"),
+    (44, 8, "SlowTrSt"), (44, 40, "Susp Str"),
+    (46, 40, "YangChin"),
+    (48, 3, "S.Strngs"), (48, 8, "SlowStr"), (48, 24, "ArcoStr"), (48, 35, "60sStrng"), (48, 40, "Orchestr"), (48, 41, "Orchstr2"), (48, 42, "TremOrch"), (48, 45, "VeloStr"),
+    (49, 3, "S.SlwStr"), (49, 8, "LegatoSt"), (49, 40, "Warm Str"), (49, 41, "Kingdom"), (49, 64, "70s Str"), (49, 65, "StrEns 3"),
+    (50, 27, "ResoStr"), (50, 64, "SynStr4"), (50, 65, "SS Str"),
+    (51, 40, "Syn Str3"),
+    (52, 3, "S.Choir"), (52, 16, "Ch.Aahs2"), (52, 32, "MelChoir"), (52, 40, "ChoirStr"),
+    (54, 40, "SynVox2"), (54, 41, "Choral"), (54, 64, "AnaVoice"),
+    (55, 35, "OrchHit2"), (55, 64, "Impact"),
+    (56, 16, "Trumpet2"), (56, 17, "BriteTrp"), (56, 32, "WarmTrp"),
+    (57, 18, "Trmbone2"),
+    (58, 16, "Tuba 2"),
+    (60, 6, "FrHrSolo"), (60, 32, "FrHorn2"), (60, 37, "HornOrch"),
+    (61, 35, "Tp&TbSec"), (61, 40, "BrssSec2"), (61, 41, "HiBrass"), (61, 42, "MelloBrs"),
+    (62, 12, "QuackBr"), (62, 20, "RezSynBr"), (62, 24, "PolyBrss"), (62, 27, "SynBras3"), (62, 32, "JumpBrss"), (62, 45, "AnaVelBr"), (62, 64, "AnaBrss1"),
+    (63, 18, "Soft Brs"), (63, 40, "SynBras4"), (63, 41, "ChoirBrs"), (63, 45, "VelBrss2"), (63, 64, "AnaBrss2"),
+    (65, 40, "Sax Sect"), (65, 43, "HyprAlto"),
+    (66, 40, "BrthTnSx"), (66, 41, "SoftTenr"), (66, 64, "TnrSax 2"),
+    (80, 6, "Square 2"), (80, 8, "LMSquare"), (80, 18, "Hollow"), (80, 19, "Shmoog"), (80, 64, "Mellow"), (80, 65, "SoloSine"), (80, 66, "SineLead"),
+    (81, 6, "Saw 2"), (81, 8, "ThickSaw"), (81, 18, "DynaSaw"), (81, 19, "DigiSaw"), (81, 20, "Big Lead"), (81, 24, "HeavySyn"), (81, 25, "WaspySyn"), (81, 40, "PulseSaw"), (81, 41, "Dr. Lead"), (81, 45, "VeloLead"), (81, 96, "Seq Ana"),
+    (82, 65, "Pure Pad"),
+    (83, 64, "Rubby"),
+    (84, 64, "DistLead"),
+    (86, 35, "Big Five"),
+    (87, 16, "Big&Low"), (87, 64, "Fat&Prky"), (87, 65, "SoftWurl"),
+    (88, 64, "Fantasy2"),
+    (89, 16, "ThickPad"), (89, 17, "Soft Pad"), (89, 18, "SinePad"), (89, 64, "Horn Pad"), (89, 65, "RotarStr"),
+    (90, 64, "PolyPd80"), (90, 65, "ClickPad"), (90, 66, "Ana Pad"), (90, 67, "SquarPad"),
+    (91, 64, "Heaven2"),
+    (93, 64, "Tine Pad"), (93, 65, "Pan Pad"),
+    (95, 20, "Shwimmer"), (95, 27, "Converge"), (95, 64, "PolarPad"), (95, 66, "Celstial"),
+    (96, 45, "ClaviPad"), (96, 64, "HrmoRain"), (96, 65, "AfrcnWnd"), (96, 66, "Carib"),
+    (97, 27, "Prologue"),
+    (98, 12, "SynDrCmp"), (98, 14, "Popcorn"), (98, 18, "TinyBell"), (98, 35, "RndGlock"), (98, 40, "GlockChi"), (98, 41, "ClearBel"), (98, 42, "ChorBell"), (98, 64, "SynMalet"), (98, 65, "SftCryst"), (98, 66, "LoudGlok"), (98, 67, "XmasBell"), (98, 68, "VibeBell"), (98, 69, "DigiBell"), (98, 70, "AirBells"), (98, 71, "BellHarp"), (98, 72, "Gamelmba"),
+    (99, 18, "WarmAtms"), (99, 19, "HollwRls"), (99, 40, "NylonEP"), (99, 64, "NylnHarp"), (99, 65, "Harp Vox"), (99, 66, "AtmosPad"), (99, 67, "Planet"),
+    (100, 64, "FantaBel"), (100, 96, "Smokey"),
+    (101, 64, "GobSynth"), (101, 65, "50sSciFi"), (101, 66, "Ring Pad"), (101, 67, "Ritual"), (101, 68, "ToHeaven"), (101, 70, "Night"), (101, 71, "Glisten"), (101, 96, "BelChoir"),
+    (102, 8, "EchoPad2"), (102, 14, "Echo Pan"), (102, 64, "EchoBell"), (102, 65, "Big Pan"), (102, 66, "SynPiano"), (102, 67, "Creation"), (102, 68, "Stardust"), (102, 69, "Reso Pan"),
+    (103, 64, "Starz"),
+    (104, 32, "DetSitar"), (104, 35, "Sitar 2"), (104, 96, "Tambra"), (104, 97, "Tamboura"),
+    (105, 28, "MuteBnjo"), (105, 96, "Rabab"), (105, 97, "Gopichnt"), (105, 98, "Oud"),
+    (106, 96, "Tsugaru"),
+    (107, 96, "T. Koto"), (107, 97, "Kanoon"),
+    (108, 64, "BigKalim"),
+    (111, 64, "Shanai2"), (111, 96, "Pungi"), (111, 97, "Hichriki"),
+    (112, 96, "Bonang"), (112, 97, "Gender"), (112, 98, "Gamelan"), (112, 99, "S.Gamlan"), (112, 100, "Rama Cym"), (112, 101, "AsianBel"),
+    (114, 97, "GlasPerc"), (114, 98, "ThaiBell"),
+    (115, 96, "Castanet"),
+    (116, 96, "Gr.Cassa"),
+    (117, 64, "Mel Tom2"), (117, 65, "Real Tom"), (117, 66, "Rock Tom"),
+    (118, 64, "Ana Tom"), (118, 65, "ElecPerc"),
+    (119, 64, "RevCym2"),
+];
+
+/// XG SFX voices selected by program number with bank MSB 64
+const XG_SFX_VOICES: [(u8, &str); 42] = [
+    (0, "CuttngNz"), (1, "CttngNz2"), (3, "Str Slap"), (16, "Fl.KClik"), (32, "Rain"), (33, "Thunder"),
+    (34, "Wind"), (35, "Stream"), (36, "Bubble"), (37, "Feed"), (48, "Dog"), (49, "Horse"),
+    (50, "Tweet 2"), (55, "Ghost"), (56, "Maou"), (64, "Tel.Dial"), (65, "DoorSqek"), (66, "DoorSlam"),
+    (67, "Scratch"), (68, "Scratch2"), (69, "WindChim"), (70, "Telphon2"), (80, "CarEngin"), (81, "Car Stop"),
+    (82, "Car Pass"), (83, "CarCrash"), (84, "Siren"), (85, "Train"), (86, "Jetplane"), (87, "Starship"),
+    (88, "Burst"), (89, "Coaster"), (90, "SbMarine"), (96, "Laughing"), (97, "Scream"), (98, "Punch"),
+    (99, "Heart"), (100, "FootStep"), (112, "MchinGun"), (113, "LaserGun"), (114, "Xplosion"), (115, "FireWork"),
+];
+
+/// GS drum sets selected by program number on the drum part
+const GS_DRUM_SETS: [(u8, &str); 10] = [
+    (0, "STANDARD"), (8, "ROOM"), (16, "POWER"), (24, "ELECTRONIC"), (25, "TR-808"),
+    (32, "JAZZ"), (40, "BRUSH"), (48, "ORCHESTRA"), (56, "SFX"), (127, "CM-64/32L"),
+];
+
+/// XG drum kits selected by program number with bank MSB 127
+const XG_DRUM_KITS: [(u8, &str); 9] = [
+    (0, "Standard Kit"), (1, "Standard Kit 2"), (8, "Room Kit"), (16, "Rock Kit"), (24, "Electro Kit"),
+    (25, "Analog Kit"), (32, "Jazz Kit"), (40, "Brush Kit"), (48, "Classic Kit"),
+];
+
+/// XG SFX kits selected by program number with bank MSB 126
+const XG_SFX_KITS: [(u8, &str); 2] = [(0, "SFX Kit 1"), (1, "SFX Kit 2")];
+
+/// GM2 percussion sets selected by program number with bank MSB 120
+const GM2_PERCUSSION_SETS: [(u8, &str); 9] = [
+    (0, "Standard Set"), (8, "Room Set"), (16, "Power Set"), (24, "Electronic Set"), (25, "Analog Set"),
+    (32, "Jazz Set"), (40, "Brush Set"), (48, "Orchestra Set"), (56, "SFX Set"),
+];
+
+/// Bank MSB of GM2 melodic sounds
+pub const GM2_MELODIC_BANK: u8 = 121;
+/// Bank MSB of GM2 percussion sets
+pub const GM2_PERCUSSION_BANK: u8 = 120;
+
+/// Returns the GM2 variation name for the program and bank LSB, falling back to the GM1 name for LSB 0
+pub fn gm2_instrument_name(program: u8, bank_lsb: u8) -> Option<&'static str> {
+    if bank_lsb == 0 {
+        return Some(instrument_name(program));
+    }
+    GM2_VARIATIONS.iter().find(|(p, lsb, _)| *p == program && *lsb == bank_lsb).map(|(_, _, name)| *name)
+}
+
+/// Returns the GS variation tone name for the program and bank MSB, falling back to the GM1 name for MSB 0.
+/// Bank MSB 127 selects the MT-32 compatible tones.
+pub fn gs_instrument_name(program: u8, bank_msb: u8) -> Option<&'static str> {
+    match bank_msb {
+        0 => return Some(instrument_name(program)),
+        127 => return MT32_INSTRUMENTS.get(program as usize).copied(),
+        _ => {}
+    }
+    GS_VARIATIONS.iter().find(|(p, msb, _)| *p == program && *msb == bank_msb).map(|(_, _, name)| *name)
+}
+
+pub fn gm2_percussion_set_name(program: u8) -> Option<&'static str> {
+    GM2_PERCUSSION_SETS.iter().find(|(p, _)| *p == program).map(|(_, name)| *name)
+}
+
+pub fn gs_drum_set_name(program: u8) -> Option<&'static str> {
+    GS_DRUM_SETS.iter().find(|(p, _)| *p == program).map(|(_, name)| *name)
+}
+
+/// Returns the XG drum or SFX kit name for bank MSB 127 or 126
+pub fn xg_drum_kit_name(program: u8, bank_msb: u8) -> Option<&'static str> {
+    let kits: &[(u8, &str)] = match bank_msb {
+        127 => &XG_DRUM_KITS,
+        126 => &XG_SFX_KITS,
+        _ => return None
+    };
+    kits.iter().find(|(p, _)| *p == program).map(|(_, name)| *name)
+}
+
+/// Returns the XG voice name for the program and bank, falling back to the GM1 name for bank 0/0.
+/// Bank MSB 0 selects melodic variations by LSB, 64 the SFX voices and 126 or 127 the drum kits.
+pub fn xg_instrument_name(program: u8, bank_msb: u8, bank_lsb: u8) -> Option<&'static str> {
+    match (bank_msb, bank_lsb) {
+        (0, 0) => Some(instrument_name(program)),
+        (0, _) => XG_VARIATIONS.iter().find(|(p, lsb, _)| *p == program && *lsb == bank_lsb).map(|(_, _, name)| *name),
+        (64, _) => XG_SFX_VOICES.iter().find(|(p, _)| *p == program).map(|(_, name)| *name),
+        _ => xg_drum_kit_name(program, bank_msb),
+    }
+}
+
+/// Returns the name of a note such as "C4" (key 60 is C4)
+pub fn note_name(key: u8) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[(key % 12) as usize], key as i32 / 12 - 1)
+}
+
+impl super::message::ChannelVoiceMessage {
+    /// Returns the GM instrument name of a ProgramChange, or the GM2 percussion set name on channel 10
+    pub fn program_name(&self) -> Option<&'static str> {
+        match *self {
+            super::message::ChannelVoiceMessage::ProgramChange{channel, pc} if channel == DRUM_CHANNEL => gm2_percussion_set_name(pc),
+            super::message::ChannelVoiceMessage::ProgramChange{pc, ..} => Some(instrument_name(pc)),
+            _ => None
+        }
+    }
+
+    /// Returns the GM instrument name of a ProgramChange, or the GS drum set name on channel 10
+    pub fn gs_program_name(&self) -> Option<&'static str> {
+        match *self {
+            super::message::ChannelVoiceMessage::ProgramChange{channel, pc} if channel == DRUM_CHANNEL => gs_drum_set_name(pc),
+            _ => self.program_name(),
+        }
+    }
+
+    /// Returns the controller name of a ControlChange, or None for undefined controllers like controller_name
+    pub fn controller_name(&self) -> Option<&'static str> {
+        match *self {
+            super::message::ChannelVoiceMessage::ControlChange{cc, ..} => controller_name(cc),
+            _ => None
+        }
+    }
+
+    /// Returns the percussion name of a note message on channel 10
+    pub fn percussion_name(&self) -> Option<&'static str> {
+        use super::message::ChannelVoiceMessage::*;
+        match *self {
+            NoteOn{channel, key, ..} | NoteOff{channel, key, ..} | PolyphonicKeyPressure{channel, key, ..} if channel == DRUM_CHANNEL => percussion_name(key),
+            _ => None
+        }
+    }
+
+    /// Returns a human readable description, e.g. "Ch.1 Program Change 33 (Electric Bass (finger))"
+    pub fn describe(&self) -> String {
+        use super::message::ChannelVoiceMessage::*;
+        let channel = self.channel() + 1;
+        let key_name = |key: u8| self.percussion_name().map_or_else(|| note_name(key), |name| name.to_string());
+        match *self {
+            NoteOn{key, vel, ..} => format!("Ch.{} Note On {} vel {}", channel, key_name(key), vel),
+            NoteOff{key, vel, ..} => format!("Ch.{} Note Off {} vel {}", channel, key_name(key), vel),
+            PolyphonicKeyPressure{key, vel, ..} => format!("Ch.{} Polyphonic Key Pressure {} {}", channel, key_name(key), vel),
+            ControlChange{cc, value, ..} => format!("Ch.{} Control Change {} ({}) {}", channel, cc, controller_description(cc), value),
+            ProgramChange{pc, ..} => format!("Ch.{} Program Change {} ({})", channel, pc, self.program_name().unwrap_or("Unknown")),
+            ChannelKeyPressure{vel, ..} => format!("Ch.{} Channel Pressure {}", channel, vel),
+            PitchBend{..} => format!("Ch.{} Pitch Bend {}", channel, self.pitch_bend_value().unwrap_or(0) as i32 - 8192),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::file::filerw::SmfError;
+use super::event::{MidiEvent, TrackChunk};
+use super::message::{ChannelVoiceMessage, MidiChannelMessage};
+use super::rpn::split_14bit;
+
+/// Offset from a MSB controller (CC 0-31) to its LSB partner (CC 32-63)
+pub const LSB_OFFSET: u8 = 32;
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Event of the 14-bit controller view of a track
+#[derive(Debug, Clone, PartialEq)]
+pub enum HighResEvent {
+    /// CC controller (0-31) and CC controller + 32 combined into a 14-bit value
+    Control{channel: u8, controller: u8, value: u16},
+    /// Any other event, including MSB or LSB controllers without their partner
+    Event(MidiEvent),
+}
+
+/// Builds the MSB and LSB controller messages of a 14-bit value. Returns None if controller is not 0-31.
+pub fn control_change_14bit(channel: u8, controller: u8, value: u16) -> Option<[MidiChannelMessage; 2]> {
+    if controller >= LSB_OFFSET {
+        return None;
+    }
+    let (msb, lsb) = split_14bit(value);
+    Some([
+        MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ControlChange{channel, cc: controller, value: msb}),
+        MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ControlChange{channel, cc: controller + LSB_OFFSET, value: lsb}),
+    ])
+}
+
+fn control_change(event: &MidiEvent) -> Option<(u8, u8, u8)> {
+    match event {
+        MidiEvent::MidiChannelMessage(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ControlChange{channel, cc, value})) => Some((*channel, *cc, *value)),
+        _ => None
+    }
+}
+
+impl TrackChunk {
+    /// Returns the track with CC 0-31 paired with CC 32-63.
+    /// A MSB is paired with its LSB when the LSB is the next controller of the channel on the same tick.
+    /// Unpaired controllers are kept as they are. from_high_res writes every LSB right after its MSB,
+    /// so events of the tick that were between a pair are moved after it; otherwise the track is restored.
+    pub fn to_high_res(&self) -> std::vec::Vec<(u32, HighResEvent)> {
+        let events = self.to_absolute();
+        let mut paired_lsb = vec![false; events.len()];
+        let mut result = std::vec::Vec::with_capacity(events.len());
+
+        for (i, (tick, event)) in events.iter().enumerate() {
+            if paired_lsb[i] {
+                continue;
+            }
+            if let Some((channel, cc, msb)) = control_change(event) {
+                if cc < LSB_OFFSET {
+                    let partner = events[i + 1..].iter().enumerate()
+                        .take_while(|(_, (t, _))| t == tick)
+                        .find_map(|(j, (_, e))| control_change(e).filter(|(ch, _, _)| *ch == channel).map(|c| (i + 1 + j, c)));
+                    if let Some((j, (_, lsb_cc, lsb))) = partner {
+                        if lsb_cc == cc + LSB_OFFSET {
+                            paired_lsb[j] = true;
+                            result.push((*tick, HighResEvent::Control{channel, controller: cc, value: ((msb as u16) << 7) | lsb as u16}));
+                            continue;
+                        }
+                    }
+                }
+            }
+            result.push((*tick, HighResEvent::Event(event.clone())));
+        }
+
+        result
+    }
+
+    /// Builds a track from the 14-bit controller view, splitting controls into MSB and LSB messages.
+    /// Fails if a control has a controller other than 0-31.
+    pub fn from_high_res(events: std::vec::Vec<(u32, HighResEvent)>) -> Result<TrackChunk> {
+        let mut flat = std::vec::Vec::with_capacity(events.len());
+        for (tick, event) in events {
+            match event {
+                HighResEvent::Control{channel, controller, value} => {
+                    let messages = control_change_14bit(channel, controller, value)
+                        .ok_or_else(|| SmfError::new("14-bit controller must be 0-31"))?;
+                    for message in messages.iter() {
+                        flat.push((tick, MidiEvent::MidiChannelMessage(message.clone())));
+                    }
+                },
+                HighResEvent::Event(event) => flat.push((tick, event)),
+            }
+        }
+        Ok(TrackChunk::from_absolute(flat))
+    }
+}
+
+impl super::state::ChannelState {
+    /// Returns the 14-bit value of controller 0-31 combined with its LSB (0 if the LSB was never set)
+    pub fn controller_14bit(&self, controller: u8) -> Option<u16> {
+        if controller >= LSB_OFFSET {
+            return None;
+        }
+        let msb = self.controller(controller)?;
+        let lsb = self.controller(controller + LSB_OFFSET).unwrap_or(0);
+        Some(((msb as u16) << 7) | lsb as u16)
+    }
+}
@@ -8,7 +8,7 @@ pub fn to_vlq(val: u32) -> std::vec::Vec<u8> {
     let mut rem = val;
     while rem != 0 {
         let seven = rem & 0b01111111;
-        rem = rem >> 7;
+        rem >>= 7;
         reverse_binary.push(seven as u8 + 128);
     }
     reverse_binary[0] -= 128;
@@ -16,7 +16,7 @@ pub fn to_vlq(val: u32) -> std::vec::Vec<u8> {
     reverse_binary
 }
 
-pub fn from_vlq(binary: &std::vec::Vec<u8>) -> u32 {
+pub fn from_vlq(binary: &[u8]) -> u32 {
     let mut ret: u32 = 0;
     for (i, bin) in binary.iter().enumerate() {
         let num =
@@ -37,13 +37,13 @@ pub trait SmfElement {
 
 // ChannelVoiceMessage and ChannelModeMessage //
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MidiChannelMessage {
     ChannelVoiceMessage(ChannelVoiceMessage),
     ChannelModeMessage(ChannelModeMessage),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ChannelVoiceMessage {
     NoteOff{channel: u8, key: u8, vel: u8},
     NoteOn{channel: u8, key: u8, vel: u8},
@@ -54,7 +54,7 @@ pub enum ChannelVoiceMessage {
     PitchBend{channel: u8, lsb: u8, msb: u8}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ChannelModeMessage {
     AllSoundOff{channel: u8},
     ResetAllControllers{channel: u8},
@@ -75,6 +75,31 @@ impl ChannelVoiceMessage {
         *ch
     }
 
+    /// Creates a PitchBend from a 14-bit value (8192 is center)
+    pub fn pitch_bend(channel: u8, value: u16) -> ChannelVoiceMessage {
+        ChannelVoiceMessage::PitchBend{channel, lsb: (value & 0x7F) as u8, msb: ((value >> 7) & 0x7F) as u8}
+    }
+
+    /// Returns the 14-bit value of a PitchBend
+    pub fn pitch_bend_value(&self) -> Option<u16> {
+        match *self {
+            ChannelVoiceMessage::PitchBend{lsb, msb, ..} => Some(((msb as u16) << 7) | lsb as u16),
+            _ => None
+        }
+    }
+
+    pub fn set_channel(&mut self, ch: u8) {
+        match self {
+            ChannelVoiceMessage::NoteOff{channel, ..} => *channel = ch,
+            ChannelVoiceMessage::NoteOn{channel, ..} => *channel = ch,
+            ChannelVoiceMessage::PolyphonicKeyPressure{channel, ..} => *channel = ch,
+            ChannelVoiceMessage::ControlChange{channel, ..} => *channel = ch,
+            ChannelVoiceMessage::ProgramChange{channel, ..} => *channel = ch,
+            ChannelVoiceMessage::ChannelKeyPressure{channel, ..} => *channel = ch,
+            ChannelVoiceMessage::PitchBend{channel, ..} => *channel = ch
+        }
+    }
+
     pub fn status_byte(&self) -> u8 {
         match self {
             ChannelVoiceMessage::NoteOff{channel, ..} => 0x80 + channel,
@@ -98,6 +123,14 @@ impl ChannelModeMessage {
         *ch
     }
 
+    pub fn set_channel(&mut self, ch: u8) {
+        match self {
+            ChannelModeMessage::AllSoundOff{channel} => *channel = ch,
+            ChannelModeMessage::ResetAllControllers{channel} => *channel = ch,
+            ChannelModeMessage::AllNotesOff{channel} => *channel = ch
+        }
+    }
+
     pub fn status_byte(&self) -> u8 {
         0xB0 + self.channel()
     }
@@ -110,6 +143,13 @@ impl MidiChannelMessage {
             MidiChannelMessage::ChannelModeMessage(message) => message.channel()
         }
     }
+
+    pub fn set_channel(&mut self, channel: u8) {
+        match self {
+            MidiChannelMessage::ChannelVoiceMessage(message) => message.set_channel(channel),
+            MidiChannelMessage::ChannelModeMessage(message) => message.set_channel(channel)
+        }
+    }
 }
 
 impl SmfElement for ChannelVoiceMessage {
@@ -122,7 +162,7 @@ impl SmfElement for ChannelVoiceMessage {
             ControlChange{cc, value, ..} => vec![self.status_byte(), cc, value],
             ProgramChange{pc, ..} => vec![self.status_byte(), pc],
             ChannelKeyPressure{vel, ..} => vec![self.status_byte(), vel],
-            PitchBend{lsb, msb, ..} => vec![self.status_byte(), msb, lsb]
+            PitchBend{lsb, msb, ..} => vec![self.status_byte(), lsb, msb]
         }
     }
 }
@@ -147,9 +187,94 @@ impl SmfElement for MidiChannelMessage {
     }
 }
 
+// System Common Messages //
+
+/// System Common messages. They never appear in SMF.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemCommonMessage {
+    /// F1: MIDI Time Code piece (0-7) and its 4-bit value
+    MtcQuarterFrame{message_type: u8, value: u8},
+    /// F2: position in MIDI beats (sixteenth notes) from the start of the song
+    SongPosition{beats: u16},
+    /// F3
+    SongSelect{song: u8},
+    /// F6
+    TuneRequest,
+}
+
+impl SystemCommonMessage {
+    pub fn status_byte(&self) -> u8 {
+        use SystemCommonMessage::*;
+        match self {
+            MtcQuarterFrame{..} => 0xF1,
+            SongPosition{..} => 0xF2,
+            SongSelect{..} => 0xF3,
+            TuneRequest => 0xF6,
+        }
+    }
+}
+
+impl SmfElement for SystemCommonMessage {
+    fn raw(&self) -> std::vec::Vec<u8> {
+        use SystemCommonMessage::*;
+        match *self {
+            MtcQuarterFrame{message_type, value} => vec![self.status_byte(), ((message_type & 0x07) << 4) | (value & 0x0F)],
+            SongPosition{beats} => vec![self.status_byte(), (beats & 0x7F) as u8, ((beats >> 7) & 0x7F) as u8],
+            SongSelect{song} => vec![self.status_byte(), song & 0x7F],
+            TuneRequest => vec![self.status_byte()],
+        }
+    }
+}
+
+// System Real-Time Messages //
+
+/// Single-byte messages which may appear anywhere in a MIDI stream. They never appear in SMF.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemRealTimeMessage {
+    TimingClock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    SystemReset,
+}
+
+impl SystemRealTimeMessage {
+    pub fn status_byte(&self) -> u8 {
+        use SystemRealTimeMessage::*;
+        match self {
+            TimingClock => 0xF8,
+            Start => 0xFA,
+            Continue => 0xFB,
+            Stop => 0xFC,
+            ActiveSensing => 0xFE,
+            SystemReset => 0xFF,
+        }
+    }
+
+    pub fn from_status_byte(status: u8) -> Option<SystemRealTimeMessage> {
+        use SystemRealTimeMessage::*;
+        match status {
+            0xF8 => Some(TimingClock),
+            0xFA => Some(Start),
+            0xFB => Some(Continue),
+            0xFC => Some(Stop),
+            0xFE => Some(ActiveSensing),
+            0xFF => Some(SystemReset),
+            _ => None
+        }
+    }
+}
+
+impl SmfElement for SystemRealTimeMessage {
+    fn raw(&self) -> std::vec::Vec<u8> {
+        vec![self.status_byte()]
+    }
+}
+
 // Meta Events // 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MetaEvent {
     SequenceNumber{number: u16},
     TextEvent{length: u32, text: std::vec::Vec<u8>},
@@ -169,6 +294,31 @@ pub enum MetaEvent {
     SequencerSpecific{length: u32, id: u8, data: u8}
 }
 
+impl MetaEvent {
+    /// Returns the meta event type byte following 0xFF
+    pub fn type_byte(&self) -> u8 {
+        use MetaEvent::*;
+        match self {
+            SequenceNumber{..} => 0x00,
+            TextEvent{..} => 0x01,
+            CopyrightNotice{..} => 0x02,
+            SequenceTrackName{..} => 0x03,
+            InstrumentName{..} => 0x04,
+            Lyric{..} => 0x05,
+            Marker{..} => 0x06,
+            CuePoint{..} => 0x07,
+            MIDIChannelPrefix{..} => 0x20,
+            SpecifyOutPort{..} => 0x21,
+            EndOfTrack => 0x2F,
+            SetTempo{..} => 0x51,
+            SMPTEOffset{..} => 0x54,
+            TimeSignature{..} => 0x58,
+            KeySignature{..} => 0x59,
+            SequencerSpecific{..} => 0x7F
+        }
+    }
+}
+
 impl SmfElement for MetaEvent {
     fn raw(&self) -> std::vec::Vec<u8> {
         fn _helper(code: u8, len: u32, text: &std::vec::Vec<u8>) -> std::vec::Vec<u8> {
@@ -209,7 +359,7 @@ impl SmfElement for MetaEvent {
 }
 
 // System Exclusive Events //
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SysExEvent {
     SysExF0{length: u32, data: std::vec::Vec<u8>},
     SysExF7{length: u32, data: std::vec::Vec<u8>}
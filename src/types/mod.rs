@@ -1,2 +1,10 @@
 pub mod event;
-pub mod message;
\ No newline at end of file
+pub mod gm;
+pub mod highres;
+pub mod message;
+pub mod roland;
+pub mod rpn;
+pub mod state;
+pub mod sysex;
+pub mod tempo;
+pub mod tuning;
@@ -0,0 +1,241 @@
+use super::message::SysExEvent;
+use super::sysex::{roland_checksum, sysex_payload, ROLAND_ID};
+use crate::file::filerw::SmfError;
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Model ID of GS devices
+pub const GS_MODEL_ID: u8 = 0x42;
+/// Default device ID of GS devices
+pub const GS_DEVICE_ID: u8 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RolandCommand {
+    /// Data set 1 (0x12)
+    DT1,
+    /// Data request 1 (0x11)
+    RQ1,
+}
+
+impl RolandCommand {
+    pub fn id(&self) -> u8 {
+        match self {
+            RolandCommand::DT1 => 0x12,
+            RolandCommand::RQ1 => 0x11,
+        }
+    }
+}
+
+/// Roland DT1/RQ1 SysEx: F0 41 dev model cmd addr(3) body checksum F7.
+/// The body is the data for DT1 and the 3-byte size for RQ1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RolandSysEx {
+    pub device_id: u8,
+    pub model_id: u8,
+    pub command: RolandCommand,
+    pub address: [u8; 3],
+    pub body: std::vec::Vec<u8>,
+}
+
+impl RolandSysEx {
+    pub fn dt1(device_id: u8, model_id: u8, address: [u8; 3], data: std::vec::Vec<u8>) -> RolandSysEx {
+        RolandSysEx {device_id, model_id, command: RolandCommand::DT1, address, body: data}
+    }
+
+    pub fn rq1(device_id: u8, model_id: u8, address: [u8; 3], size: [u8; 3]) -> RolandSysEx {
+        RolandSysEx {device_id, model_id, command: RolandCommand::RQ1, address, body: size.to_vec()}
+    }
+
+    /// Checksum over address and body
+    pub fn checksum(&self) -> u8 {
+        let mut bytes = self.address.to_vec();
+        bytes.extend(&self.body);
+        roland_checksum(&bytes)
+    }
+
+    /// Parses the bytes between F0 and F7, verifying the checksum
+    pub fn parse_payload(payload: &[u8]) -> Result<RolandSysEx> {
+        match payload {
+            [ROLAND_ID, device_id, model_id, command, a0, a1, a2, body @ .., checksum] => {
+                let command = match command {
+                    0x12 => RolandCommand::DT1,
+                    0x11 => RolandCommand::RQ1,
+                    c => return Err(SmfError::new(&format!("unsupported Roland command 0x{:02X}", c))),
+                };
+                if command == RolandCommand::RQ1 && body.len() != 3 {
+                    return Err(SmfError::new("RQ1 size must be 3 bytes"));
+                }
+                let message = RolandSysEx {device_id: *device_id, model_id: *model_id, command, address: [*a0, *a1, *a2], body: body.to_vec()};
+                if message.checksum() != *checksum {
+                    return Err(SmfError::new(&format!("Roland checksum mismatch: expected 0x{:02X}, got 0x{:02X}", message.checksum(), checksum)));
+                }
+                Ok(message)
+            },
+            _ => Err(SmfError::new("not a Roland DT1/RQ1 message"))
+        }
+    }
+
+    pub fn parse(event: &SysExEvent) -> Result<RolandSysEx> {
+        RolandSysEx::parse_payload(sysex_payload(event))
+    }
+
+    /// Returns the bytes between F0 and F7 with the checksum calculated
+    pub fn payload(&self) -> std::vec::Vec<u8> {
+        let mut data = vec![ROLAND_ID, self.device_id, self.model_id, self.command.id()];
+        data.extend(self.address);
+        data.extend(&self.body);
+        data.push(self.checksum());
+        data
+    }
+
+    pub fn to_event(&self) -> SysExEvent {
+        let mut data = self.payload();
+        data.push(0xF7);
+        SysExEvent::SysExF0{length: data.len() as u32, data}
+    }
+
+    /// Returns the GS parameter at the address of a GS message
+    pub fn gs_parameter(&self) -> Option<GsParameter> {
+        if self.model_id == GS_MODEL_ID {
+            GsParameter::from_address(self.address)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the GS part block (x of 40 1x yy) of a MIDI channel.
+/// Block 0 is part 10, blocks 1-9 are parts 1-9 and blocks A-F are parts 11-16.
+pub fn gs_part_block(channel: u8) -> u8 {
+    match channel & 0x0F {
+        9 => 0,
+        ch if ch < 9 => ch + 1,
+        ch => ch,
+    }
+}
+
+/// MIDI channel of a GS part block. Inverse of gs_part_block.
+pub fn gs_block_channel(block: u8) -> u8 {
+    match block & 0x0F {
+        0 => 9,
+        b if b <= 9 => b - 1,
+        b => b,
+    }
+}
+
+/// GS parameters and their addresses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GsParameter {
+    /// 40 00 04, 0-127
+    MasterVolume,
+    /// 40 00 05, 40-88 (-24 to +24 semitones)
+    MasterKeyShift,
+    /// 40 00 06, 1-127 (64 is center)
+    MasterPan,
+    /// 40 01 30, 0-7 (Room 1 ... Panning Delay)
+    ReverbMacro,
+    /// 40 01 31, 0-7
+    ReverbCharacter,
+    /// 40 01 33, 0-127
+    ReverbLevel,
+    /// 40 01 34, 0-127
+    ReverbTime,
+    /// 40 01 35, 0-127
+    ReverbDelayFeedback,
+    /// 40 01 38, 0-7 (Chorus 1 ... Flanger)
+    ChorusMacro,
+    /// 40 01 3A, 0-127
+    ChorusLevel,
+    /// 40 01 3B, 0-127
+    ChorusFeedback,
+    /// 40 01 3C, 0-127
+    ChorusDelay,
+    /// 40 01 3D, 0-127
+    ChorusRate,
+    /// 40 01 3E, 0-127
+    ChorusDepth,
+    /// 40 1x 02, receive channel of the part on the channel
+    PartRxChannel(u8),
+    /// 40 1x 15, drum map assignment: 0 off, 1 map 1, 2 map 2
+    PartUseForRhythm(u8),
+    /// 40 1x 16, 40-88 (-24 to +24 semitones)
+    PartKeyShift(u8),
+    /// 40 1x 19, 0-127
+    PartLevel(u8),
+    /// 40 1x 1C, 0 random, 1-127
+    PartPan(u8),
+    /// 40 1x 21, 0-127
+    PartChorusSend(u8),
+    /// 40 1x 22, 0-127
+    PartReverbSend(u8),
+}
+
+impl GsParameter {
+    pub fn address(&self) -> [u8; 3] {
+        use GsParameter::*;
+        let part = |channel: &u8, offset: u8| [0x40, 0x10 | gs_part_block(*channel), offset];
+        match self {
+            MasterVolume => [0x40, 0x00, 0x04],
+            MasterKeyShift => [0x40, 0x00, 0x05],
+            MasterPan => [0x40, 0x00, 0x06],
+            ReverbMacro => [0x40, 0x01, 0x30],
+            ReverbCharacter => [0x40, 0x01, 0x31],
+            ReverbLevel => [0x40, 0x01, 0x33],
+            ReverbTime => [0x40, 0x01, 0x34],
+            ReverbDelayFeedback => [0x40, 0x01, 0x35],
+            ChorusMacro => [0x40, 0x01, 0x38],
+            ChorusLevel => [0x40, 0x01, 0x3A],
+            ChorusFeedback => [0x40, 0x01, 0x3B],
+            ChorusDelay => [0x40, 0x01, 0x3C],
+            ChorusRate => [0x40, 0x01, 0x3D],
+            ChorusDepth => [0x40, 0x01, 0x3E],
+            PartRxChannel(ch) => part(ch, 0x02),
+            PartUseForRhythm(ch) => part(ch, 0x15),
+            PartKeyShift(ch) => part(ch, 0x16),
+            PartLevel(ch) => part(ch, 0x19),
+            PartPan(ch) => part(ch, 0x1C),
+            PartChorusSend(ch) => part(ch, 0x21),
+            PartReverbSend(ch) => part(ch, 0x22),
+        }
+    }
+
+    pub fn from_address(address: [u8; 3]) -> Option<GsParameter> {
+        use GsParameter::*;
+        let parameter = match address {
+            [0x40, 0x00, 0x04] => MasterVolume,
+            [0x40, 0x00, 0x05] => MasterKeyShift,
+            [0x40, 0x00, 0x06] => MasterPan,
+            [0x40, 0x01, 0x30] => ReverbMacro,
+            [0x40, 0x01, 0x31] => ReverbCharacter,
+            [0x40, 0x01, 0x33] => ReverbLevel,
+            [0x40, 0x01, 0x34] => ReverbTime,
+            [0x40, 0x01, 0x35] => ReverbDelayFeedback,
+            [0x40, 0x01, 0x38] => ChorusMacro,
+            [0x40, 0x01, 0x3A] => ChorusLevel,
+            [0x40, 0x01, 0x3B] => ChorusFeedback,
+            [0x40, 0x01, 0x3C] => ChorusDelay,
+            [0x40, 0x01, 0x3D] => ChorusRate,
+            [0x40, 0x01, 0x3E] => ChorusDepth,
+            [0x40, block, offset] if block & 0xF0 == 0x10 => {
+                let ch = gs_block_channel(block);
+                match offset {
+                    0x02 => PartRxChannel(ch),
+                    0x15 => PartUseForRhythm(ch),
+                    0x16 => PartKeyShift(ch),
+                    0x19 => PartLevel(ch),
+                    0x1C => PartPan(ch),
+                    0x21 => PartChorusSend(ch),
+                    0x22 => PartReverbSend(ch),
+                    _ => return None
+                }
+            },
+            _ => return None
+        };
+        Some(parameter)
+    }
+
+    /// Builds a DT1 message setting the parameter on the default GS device
+    pub fn set(&self, value: u8) -> RolandSysEx {
+        RolandSysEx::dt1(GS_DEVICE_ID, GS_MODEL_ID, self.address(), vec![value & 0x7F])
+    }
+}
@@ -0,0 +1,205 @@
+use super::event::{MidiEvent, TrackChunk};
+use super::message::{ChannelVoiceMessage, MidiChannelMessage};
+use super::state::{ChannelState, Parameter};
+
+/// RPN null: deselects the parameter so that stray data entry has no effect
+pub const RPN_NULL: u16 = 0x3FFF;
+
+/// Registered parameters defined by GM/GM2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisteredParameter {
+    PitchBendSensitivity,
+    FineTuning,
+    CoarseTuning,
+    TuningProgramSelect,
+    TuningBankSelect,
+    ModulationDepthRange,
+    Null,
+    Other(u16),
+}
+
+impl RegisteredParameter {
+    pub fn from_number(number: u16) -> RegisteredParameter {
+        use RegisteredParameter::*;
+        match number {
+            0 => PitchBendSensitivity,
+            1 => FineTuning,
+            2 => CoarseTuning,
+            3 => TuningProgramSelect,
+            4 => TuningBankSelect,
+            5 => ModulationDepthRange,
+            RPN_NULL => Null,
+            n => Other(n),
+        }
+    }
+
+    pub fn number(&self) -> u16 {
+        use RegisteredParameter::*;
+        match self {
+            PitchBendSensitivity => 0,
+            FineTuning => 1,
+            CoarseTuning => 2,
+            TuningProgramSelect => 3,
+            TuningBankSelect => 4,
+            ModulationDepthRange => 5,
+            Null => RPN_NULL,
+            Other(n) => *n,
+        }
+    }
+
+    /// Power-on value following GM2, or None if unknown
+    pub fn default_value(&self) -> Option<u16> {
+        use RegisteredParameter::*;
+        match self {
+            PitchBendSensitivity => Some(join_14bit(2, 0)),
+            FineTuning | CoarseTuning => Some(join_14bit(64, 0)),
+            TuningProgramSelect | TuningBankSelect => Some(0),
+            // 50 cents
+            ModulationDepthRange => Some(join_14bit(0, 64)),
+            Null | Other(_) => None,
+        }
+    }
+}
+
+/// Splits a 14-bit parameter number or value into (MSB, LSB)
+pub fn split_14bit(value: u16) -> (u8, u8) {
+    (((value >> 7) & 0x7F) as u8, (value & 0x7F) as u8)
+}
+
+pub fn join_14bit(msb: u8, lsb: u8) -> u16 {
+    ((msb as u16 & 0x7F) << 7) | (lsb as u16 & 0x7F)
+}
+
+/// A logical RPN/NRPN change reconstructed from controller messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterChange {
+    /// Absolute tick of the last data entry message
+    pub tick: u32,
+    /// Index of the last data entry message in TrackChunk::events
+    pub index: usize,
+    pub channel: u8,
+    pub parameter: Parameter,
+    /// 14-bit value (data entry MSB << 7 | LSB)
+    pub value: u16,
+}
+
+impl ParameterChange {
+    pub fn registered(&self) -> Option<RegisteredParameter> {
+        match self.parameter {
+            Parameter::Rpn(number) => Some(RegisteredParameter::from_number(number)),
+            Parameter::Nrpn(_) => None,
+        }
+    }
+
+    /// Pitch bend sensitivity as (semitones, cents)
+    pub fn pitch_bend_sensitivity(&self) -> Option<(u8, u8)> {
+        match self.registered() {
+            Some(RegisteredParameter::PitchBendSensitivity) => Some(split_14bit(self.value)),
+            _ => None
+        }
+    }
+
+    /// Fine tuning in cents (-100 to +100)
+    pub fn fine_tuning_cents(&self) -> Option<f64> {
+        match self.registered() {
+            Some(RegisteredParameter::FineTuning) => Some((self.value as f64 - 8192.0) / 8192.0 * 100.0),
+            _ => None
+        }
+    }
+
+    /// Coarse tuning in semitones (-64 to +63). Only the MSB is used.
+    pub fn coarse_tuning_semitones(&self) -> Option<i8> {
+        match self.registered() {
+            Some(RegisteredParameter::CoarseTuning) => Some(split_14bit(self.value).0 as i8 - 64),
+            _ => None
+        }
+    }
+}
+
+fn is_data_entry(cc: u8) -> bool {
+    matches!(cc, 6 | 38 | 96 | 97)
+}
+
+/// Reconstructs RPN/NRPN changes from CC 98-101 selection and CC 6/38/96/97 data entry.
+/// Data entry MSB followed by LSB on the same tick is reported as one change.
+pub fn decode_parameters(track: &TrackChunk) -> std::vec::Vec<ParameterChange> {
+    let mut states = vec![ChannelState::new(); 16];
+    let mut changes: std::vec::Vec<ParameterChange> = std::vec::Vec::new();
+    // Index in changes of the latest change on every channel, so interleaved channels merge their LSB correctly
+    let mut last_changes: [Option<usize>; 16] = [None; 16];
+
+    for (index, (tick, pair)) in track.absolute_ticks().into_iter().zip(track.events.iter()).enumerate() {
+        let message = match pair.event() {
+            MidiEvent::MidiChannelMessage(message) => message,
+            _ => continue,
+        };
+        let channel = message.channel() & 0x0F;
+        let state = &mut states[channel as usize];
+        state.apply(message);
+
+        if let MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ControlChange{cc, ..}) = message {
+            if !is_data_entry(*cc) {
+                continue;
+            }
+            let parameter = match state.selected {
+                Some(parameter) => parameter,
+                None => continue,
+            };
+            let value = state.parameters[&parameter];
+
+            match last_changes[channel as usize].map(|last| &mut changes[last]) {
+                Some(last) if *cc == 38 && last.tick == tick && last.parameter == parameter => {
+                    last.value = value;
+                    last.index = index;
+                },
+                _ => {
+                    last_changes[channel as usize] = Some(changes.len());
+                    changes.push(ParameterChange {tick, index, channel, parameter, value});
+                },
+            }
+        }
+    }
+
+    changes
+}
+
+fn cc(channel: u8, cc: u8, value: u8) -> MidiChannelMessage {
+    MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ControlChange{channel, cc, value})
+}
+
+/// Builds the controller messages that set a parameter to a 14-bit value.
+/// If reset is true, the null RPN is selected afterwards.
+pub fn encode_parameter(channel: u8, parameter: Parameter, value: u16, reset: bool) -> std::vec::Vec<MidiChannelMessage> {
+    let (number, msb_cc, lsb_cc) = match parameter {
+        Parameter::Rpn(number) => (number, 101, 100),
+        Parameter::Nrpn(number) => (number, 99, 98),
+    };
+    let (number_msb, number_lsb) = split_14bit(number);
+    let (value_msb, value_lsb) = split_14bit(value);
+
+    let mut messages = vec![
+        cc(channel, msb_cc, number_msb),
+        cc(channel, lsb_cc, number_lsb),
+        cc(channel, 6, value_msb),
+        cc(channel, 38, value_lsb),
+    ];
+    if reset {
+        messages.push(cc(channel, 101, 0x7F));
+        messages.push(cc(channel, 100, 0x7F));
+    }
+    messages
+}
+
+/// Builds messages setting pitch bend sensitivity, followed by the null RPN
+pub fn pitch_bend_sensitivity(channel: u8, semitones: u8, cents: u8) -> std::vec::Vec<MidiChannelMessage> {
+    encode_parameter(channel, Parameter::Rpn(0), join_14bit(semitones, cents), true)
+}
+
+impl TrackChunk {
+    /// Inserts the controller messages setting a parameter at the tick. See encode_parameter.
+    pub fn insert_parameter(&mut self, tick: u32, channel: u8, parameter: Parameter, value: u16, reset: bool) {
+        for message in encode_parameter(channel, parameter, value, reset) {
+            self.insert_event(tick, MidiEvent::MidiChannelMessage(message));
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use super::event::{MidiEvent, SMF};
+use super::message::{ChannelModeMessage, ChannelVoiceMessage, MetaEvent, MidiChannelMessage};
+use super::tempo::DEFAULT_TEMPO;
+
+/// Center value of pitch bend
+pub const PITCH_BEND_CENTER: u16 = 8192;
+
+/// Controllers reset by Reset All Controllers (CC 121), following GM RP-15.
+/// Pitch bend and channel pressure are reset as well.
+pub const RESET_CONTROLLERS: [u8; 10] = [1, 11, 64, 65, 66, 67, 98, 99, 100, 101];
+
+/// Power-on values of controllers not reset by Reset All Controllers. Other controllers default to 0.
+const CONTROLLER_DEFAULTS: [(u8, u8); 4] = [(7, 100), (8, 64), (10, 64), (91, 40)];
+
+/// Registered or non-registered parameter selected by CC 98-101
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    /// RPN number (CC 101 << 7 | CC 100)
+    Rpn(u16),
+    /// NRPN number (CC 99 << 7 | CC 98)
+    Nrpn(u16),
+}
+
+/// Values in effect on a MIDI channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelState {
+    pub program: Option<u8>,
+    /// Last value of every controller (CC 0-119). Bank select is CC 0 (MSB) and CC 32 (LSB).
+    pub controllers: [Option<u8>; 120],
+    /// 14-bit pitch bend value
+    pub pitch_bend: u16,
+    pub channel_pressure: Option<u8>,
+    /// 14-bit values set by data entry, keyed by parameter
+    pub parameters: std::collections::HashMap<Parameter, u16>,
+    /// Parameter that data entry currently writes to
+    pub selected: Option<Parameter>,
+    /// (key, velocity) of notes sounding, in the order they started
+    pub sounding: std::vec::Vec<(u8, u8)>,
+    /// Partially selected parameter number: (is_rpn, msb, lsb)
+    selecting: (bool, Option<u8>, Option<u8>),
+}
+
+impl Default for ChannelState {
+    fn default() -> ChannelState {
+        ChannelState {
+            program: None,
+            controllers: [None; 120],
+            pitch_bend: PITCH_BEND_CENTER,
+            channel_pressure: None,
+            parameters: std::collections::HashMap::new(),
+            selected: None,
+            sounding: std::vec::Vec::new(),
+            selecting: (true, None, None),
+        }
+    }
+}
+
+impl ChannelState {
+    pub fn new() -> ChannelState {
+        ChannelState::default()
+    }
+
+    pub fn controller(&self, cc: u8) -> Option<u8> {
+        self.controllers.get(cc as usize).copied().flatten()
+    }
+
+    /// Returns (CC 0, CC 32)
+    pub fn bank(&self) -> (Option<u8>, Option<u8>) {
+        (self.controller(0), self.controller(32))
+    }
+
+    pub fn rpn(&self, number: u16) -> Option<u16> {
+        self.parameters.get(&Parameter::Rpn(number)).copied()
+    }
+
+    pub fn nrpn(&self, number: u16) -> Option<u16> {
+        self.parameters.get(&Parameter::Nrpn(number)).copied()
+    }
+
+    /// Pitch bend sensitivity (RPN 0) as (semitones, cents)
+    pub fn pitch_bend_range(&self) -> Option<(u8, u8)> {
+        self.rpn(0).map(|value| ((value >> 7) as u8, (value & 0x7F) as u8))
+    }
+
+    pub fn is_sounding(&self, key: u8) -> bool {
+        self.sounding.iter().any(|(k, _)| *k == key)
+    }
+
+    fn select(&mut self, rpn: bool, msb: Option<u8>, lsb: Option<u8>) {
+        // Switching between RPN and NRPN starts a new selection
+        let (was_rpn, old_msb, old_lsb) = self.selecting;
+        let (msb, lsb) = if was_rpn == rpn { (msb.or(old_msb), lsb.or(old_lsb)) } else { (msb, lsb) };
+        self.selecting = (rpn, msb, lsb);
+
+        self.selected = match (msb, lsb) {
+            (Some(0x7F), Some(0x7F)) => None, // null RPN
+            (Some(msb), Some(lsb)) => {
+                let number = ((msb as u16) << 7) | lsb as u16;
+                Some(if rpn { Parameter::Rpn(number) } else { Parameter::Nrpn(number) })
+            },
+            _ => None
+        };
+    }
+
+    fn data_entry(&mut self, f: impl FnOnce(Option<u16>) -> u16) {
+        if let Some(parameter) = self.selected {
+            let value = f(self.parameters.get(&parameter).copied());
+            self.parameters.insert(parameter, value & 0x3FFF);
+        }
+    }
+
+    fn control_change(&mut self, cc: u8, value: u8) {
+        match cc {
+            6 => self.data_entry(|_| (value as u16) << 7),
+            38 => self.data_entry(|old| (old.unwrap_or(0) & !0x7F) | value as u16),
+            96 => self.data_entry(|old| old.unwrap_or(0).saturating_add(1).min(0x3FFF)),
+            97 => self.data_entry(|old| old.unwrap_or(0).saturating_sub(1)),
+            98 => self.select(false, None, Some(value)),
+            99 => self.select(false, Some(value), None),
+            100 => self.select(true, None, Some(value)),
+            101 => self.select(true, Some(value), None),
+            120 | 123..=127 => {
+                self.sounding.clear();
+                return;
+            },
+            121 => {
+                self.reset_controllers();
+                return;
+            },
+            _ => {}
+        }
+        if let Some(slot) = self.controllers.get_mut(cc as usize) {
+            *slot = Some(value);
+        }
+    }
+
+    /// Reset All Controllers as defined by GM (RP-15)
+    fn reset_controllers(&mut self) {
+        for cc in RESET_CONTROLLERS {
+            self.controllers[cc as usize] = None;
+        }
+        self.pitch_bend = PITCH_BEND_CENTER;
+        self.channel_pressure = None;
+        self.selected = None;
+        self.selecting = (true, None, None);
+    }
+
+    /// Messages which bring a channel from its power-on state to this state:
+    /// bank select, program, controllers, data entry of every parameter, pitch bend and channel pressure.
+    /// Sounding notes are not included.
+    pub fn restore_messages(&self, channel: u8) -> std::vec::Vec<MidiChannelMessage> {
+        let cc = |cc: u8, value: u8| MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ControlChange{channel, cc, value});
+        let mut messages = std::vec::Vec::new();
+        let (msb, lsb) = self.bank();
+        messages.extend(msb.map(|value| cc(0, value)));
+        messages.extend(lsb.map(|value| cc(32, value)));
+        if let Some(pc) = self.program {
+            messages.push(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ProgramChange{channel, pc}));
+        }
+        for (number, value) in self.controllers.iter().enumerate() {
+            match (number, value) {
+                // Bank select is sent before the program, parameters are sent below
+                (0 | 32 | 6 | 38 | 96..=101, _) | (_, None) => {},
+                (number, Some(value)) => messages.push(cc(number as u8, *value)),
+            }
+        }
+        let mut parameters: std::vec::Vec<(&Parameter, &u16)> = self.parameters.iter().collect();
+        parameters.sort_by_key(|(parameter, _)| match parameter {
+            Parameter::Rpn(number) => (0, *number),
+            Parameter::Nrpn(number) => (1, *number),
+        });
+        for (parameter, value) in parameters {
+            messages.extend(super::rpn::encode_parameter(channel, *parameter, *value, true));
+        }
+        if self.pitch_bend != PITCH_BEND_CENTER {
+            messages.push(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::pitch_bend(channel, self.pitch_bend)));
+        }
+        if let Some(vel) = self.channel_pressure {
+            messages.push(MidiChannelMessage::ChannelVoiceMessage(ChannelVoiceMessage::ChannelKeyPressure{channel, vel}));
+        }
+        messages
+    }
+
+    /// Like restore_messages, but for a channel currently in the previous state rather than at power-on.
+    /// Bank, program, controllers and registered parameters set in previous but not in this state
+    /// are returned to their defaults, since Reset All Controllers does not reset them.
+    pub fn restore_messages_from(&self, previous: &ChannelState, channel: u8) -> std::vec::Vec<MidiChannelMessage> {
+        let mut target = self.clone();
+        for (number, value) in previous.controllers.iter().enumerate() {
+            let number = number as u8;
+            if value.is_some() && target.controller(number).is_none() && !RESET_CONTROLLERS.contains(&number) {
+                let default = CONTROLLER_DEFAULTS.iter().find(|(cc, _)| *cc == number).map_or(0, |(_, value)| *value);
+                target.controllers[number as usize] = Some(default);
+            }
+        }
+        let bank_changed = target.bank() != previous.bank();
+        if target.program.is_none() && (previous.program.is_some() || bank_changed) {
+            target.program = Some(0);
+        }
+        for parameter in previous.parameters.keys() {
+            if let (Parameter::Rpn(number), false) = (parameter, target.parameters.contains_key(parameter)) {
+                if let Some(default) = super::rpn::RegisteredParameter::from_number(*number).default_value() {
+                    target.parameters.insert(*parameter, default);
+                }
+            }
+        }
+        target.restore_messages(channel)
+    }
+
+    pub fn apply(&mut self, message: &MidiChannelMessage) {
+        use ChannelVoiceMessage::*;
+        match message {
+            MidiChannelMessage::ChannelVoiceMessage(cvm) => match *cvm {
+                NoteOn{key, vel, ..} if vel > 0 => self.sounding.push((key, vel)),
+                NoteOn{key, ..} | NoteOff{key, ..} => {
+                    if let Some(i) = self.sounding.iter().position(|(k, _)| *k == key) {
+                        self.sounding.remove(i);
+                    }
+                },
+                PolyphonicKeyPressure{..} => {},
+                ControlChange{cc, value, ..} => self.control_change(cc, value),
+                ProgramChange{pc, ..} => self.program = Some(pc),
+                ChannelKeyPressure{vel, ..} => self.channel_pressure = Some(vel),
+                PitchBend{..} => self.pitch_bend = cvm.pitch_bend_value().unwrap_or(PITCH_BEND_CENTER),
+            },
+            MidiChannelMessage::ChannelModeMessage(cmm) => match cmm {
+                ChannelModeMessage::AllSoundOff{..} | ChannelModeMessage::AllNotesOff{..} => self.sounding.clear(),
+                ChannelModeMessage::ResetAllControllers{..} => self.reset_controllers(),
+            },
+        }
+    }
+}
+
+/// State of all 16 channels and the tempo
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiState {
+    pub channels: std::vec::Vec<ChannelState>,
+    /// Microseconds per quarter note
+    pub tempo: u32,
+}
+
+impl Default for MidiState {
+    fn default() -> MidiState {
+        MidiState {channels: vec![ChannelState::default(); 16], tempo: DEFAULT_TEMPO}
+    }
+}
+
+impl MidiState {
+    pub fn new() -> MidiState {
+        MidiState::default()
+    }
+
+    pub fn channel(&self, channel: u8) -> &ChannelState {
+        &self.channels[(channel & 0x0F) as usize]
+    }
+
+    pub fn apply(&mut self, event: &MidiEvent) {
+        match event {
+            MidiEvent::MidiChannelMessage(message) => self.channels[(message.channel() & 0x0F) as usize].apply(message),
+            MidiEvent::MetaEvent(MetaEvent::SetTempo{tempo}) => self.tempo = *tempo,
+            _ => {}
+        }
+    }
+
+    /// Replays events of all tracks with absolute tick < until (or <= until if inclusive)
+    fn replay(smf: &SMF, until: u32, inclusive: bool) -> MidiState {
+        let mut events: std::vec::Vec<(u32, usize, MidiEvent)> = std::vec::Vec::new();
+        for (t, track) in smf.tracks().iter().enumerate() {
+            for (tick, event) in track.to_absolute() {
+                if tick < until || (inclusive && tick == until) {
+                    events.push((tick, t, event));
+                }
+            }
+        }
+        // Stable: keeps the order inside each track
+        events.sort_by_key(|(tick, t, _)| (*tick, *t));
+
+        let mut state = MidiState::new();
+        for (_, _, event) in &events {
+            state.apply(event);
+        }
+        state
+    }
+}
+
+impl SMF {
+    /// Returns the state after every event before the tick, which is what a player seeking to the tick needs
+    pub fn state_before(&self, tick: u32) -> MidiState {
+        MidiState::replay(self, tick, false)
+    }
+
+    /// Returns the state after every event up to and including the tick
+    pub fn state_at(&self, tick: u32) -> MidiState {
+        MidiState::replay(self, tick, true)
+    }
+}
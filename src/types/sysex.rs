@@ -0,0 +1,350 @@
+use super::message::SysExEvent;
+use crate::file::filerw::SmfError;
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Device ID which addresses every device
+pub const ALL_CALL: u8 = 0x7F;
+
+pub const UNIVERSAL_NON_REAL_TIME: u8 = 0x7E;
+pub const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+pub const ROLAND_ID: u8 = 0x41;
+pub const YAMAHA_ID: u8 = 0x43;
+
+/// Roland checksum: the value which makes the sum of address, data and checksum a multiple of 128
+pub fn roland_checksum(bytes: &[u8]) -> u8 {
+    let sum = bytes.iter().fold(0u32, |acc, b| acc + *b as u32);
+    ((128 - sum % 128) % 128) as u8
+}
+
+/// Decoded SysEx message
+#[derive(Debug, Clone, PartialEq)]
+pub enum SysExMessage {
+    /// F0 7E dev 09 01 F7
+    GmSystemOn{device_id: u8},
+    /// F0 7E dev 09 02 F7
+    GmSystemOff{device_id: u8},
+    /// F0 7E dev 09 03 F7
+    Gm2SystemOn{device_id: u8},
+    /// F0 7F dev 04 01 lsb msb F7. 14-bit volume.
+    MasterVolume{device_id: u8, value: u16},
+    /// F0 7F dev 04 03 lsb msb F7. 14-bit value, 8192 is A440.
+    MasterFineTuning{device_id: u8, value: u16},
+    /// F0 7F dev 04 04 00 msb F7. 64 is no transposition.
+    MasterCoarseTuning{device_id: u8, value: u8},
+    /// F0 7E dev 06 01 F7
+    IdentityRequest{device_id: u8},
+    /// F0 7E dev 06 02 manufacturer family(2) member(2) version(4) F7
+    IdentityReply{device_id: u8, manufacturer: std::vec::Vec<u8>, family: u16, member: u16, version: [u8; 4]},
+    /// Universal MIDI Tuning Standard message (sub-ID#1 08), payload after sub-ID#2
+    MidiTuning{realtime: bool, device_id: u8, sub_id: u8, data: std::vec::Vec<u8>},
+    /// F0 41 dev 42 12 40 00 7F 00 41 F7
+    GsReset{device_id: u8},
+    /// F0 43 1n 4C 00 00 7E 00 F7
+    XgSystemOn{device_id: u8},
+    /// Roland DT1/RQ1 other than GS reset, with a verified checksum
+    Roland(super::roland::RolandSysEx),
+    /// Anything else. Bytes between F0 and F7.
+    Other(std::vec::Vec<u8>),
+}
+
+/// GS reset address and data, followed by the checksum
+const GS_RESET_BODY: [u8; 4] = [0x40, 0x00, 0x7F, 0x00];
+
+/// Returns the bytes between F0 and the trailing F7 of a SysEx event
+pub fn sysex_payload(event: &SysExEvent) -> &[u8] {
+    let data = match event {
+        SysExEvent::SysExF0{data, ..} | SysExEvent::SysExF7{data, ..} => data,
+    };
+    match data.last() {
+        Some(0xF7) => &data[..data.len() - 1],
+        _ => &data[..]
+    }
+}
+
+fn join_lsb_msb(lsb: u8, msb: u8) -> u16 {
+    ((msb as u16) << 7) | lsb as u16
+}
+
+impl SysExMessage {
+    /// Decodes a complete F0 SysEx. Returns an error if a GS reset checksum does not match;
+    /// other Roland messages that cannot be parsed decode to Other.
+    pub fn decode(event: &SysExEvent) -> Result<SysExMessage> {
+        if let SysExEvent::SysExF7{..} = event {
+            return Err(SmfError::new("F7 packet is not a complete SysEx message"));
+        }
+        SysExMessage::decode_payload(sysex_payload(event))
+    }
+
+    /// Decodes the bytes between F0 and F7
+    pub fn decode_payload(payload: &[u8]) -> Result<SysExMessage> {
+        use SysExMessage::*;
+        let message = match payload {
+            [UNIVERSAL_NON_REAL_TIME, dev, 0x09, 0x01] => GmSystemOn{device_id: *dev},
+            [UNIVERSAL_NON_REAL_TIME, dev, 0x09, 0x02] => GmSystemOff{device_id: *dev},
+            [UNIVERSAL_NON_REAL_TIME, dev, 0x09, 0x03] => Gm2SystemOn{device_id: *dev},
+            [UNIVERSAL_REAL_TIME, dev, 0x04, 0x01, lsb, msb] => MasterVolume{device_id: *dev, value: join_lsb_msb(*lsb, *msb)},
+            [UNIVERSAL_REAL_TIME, dev, 0x04, 0x03, lsb, msb] => MasterFineTuning{device_id: *dev, value: join_lsb_msb(*lsb, *msb)},
+            [UNIVERSAL_REAL_TIME, dev, 0x04, 0x04, _, msb] => MasterCoarseTuning{device_id: *dev, value: *msb},
+            [UNIVERSAL_NON_REAL_TIME, dev, 0x06, 0x01] => IdentityRequest{device_id: *dev},
+            [UNIVERSAL_NON_REAL_TIME, dev, 0x06, 0x02, rest @ ..] => {
+                // Manufacturer ID is 1 byte, or 3 bytes starting with 00
+                let id_length = if rest.first() == Some(&0x00) { 3 } else { 1 };
+                if rest.len() != id_length + 8 {
+                    return Err(SmfError::new("invalid identity reply length"));
+                }
+                let (manufacturer, info) = rest.split_at(id_length);
+                IdentityReply {
+                    device_id: *dev,
+                    manufacturer: manufacturer.to_vec(),
+                    family: join_lsb_msb(info[0], info[1]),
+                    member: join_lsb_msb(info[2], info[3]),
+                    version: [info[4], info[5], info[6], info[7]],
+                }
+            },
+            [id @ (UNIVERSAL_NON_REAL_TIME | UNIVERSAL_REAL_TIME), dev, 0x08, sub_id, data @ ..] => MidiTuning {
+                realtime: *id == UNIVERSAL_REAL_TIME,
+                device_id: *dev,
+                sub_id: *sub_id,
+                data: data.to_vec(),
+            },
+            [ROLAND_ID, dev, 0x42, 0x12, body @ .., checksum] if body == GS_RESET_BODY => {
+                if roland_checksum(body) != *checksum {
+                    return Err(SmfError::new(&format!("GS reset checksum mismatch: expected 0x{:02X}, got 0x{:02X}", roland_checksum(body), checksum)));
+                }
+                GsReset{device_id: *dev}
+            },
+            [YAMAHA_ID, dev, 0x4C, 0x00, 0x00, 0x7E, 0x00] if dev & 0xF0 == 0x10 => XgSystemOn{device_id: dev & 0x0F},
+            // Roland messages that do not parse (unknown model ID length, bad checksum) are kept opaque
+            [ROLAND_ID, _, _, 0x11 | 0x12, ..] => super::roland::RolandSysEx::parse_payload(payload)
+                .map_or_else(|_| Other(payload.to_vec()), Roland),
+            _ => Other(payload.to_vec()),
+        };
+        Ok(message)
+    }
+
+    /// Returns the bytes between F0 and F7
+    pub fn payload(&self) -> std::vec::Vec<u8> {
+        use SysExMessage::*;
+        match self {
+            GmSystemOn{device_id} => vec![UNIVERSAL_NON_REAL_TIME, *device_id, 0x09, 0x01],
+            GmSystemOff{device_id} => vec![UNIVERSAL_NON_REAL_TIME, *device_id, 0x09, 0x02],
+            Gm2SystemOn{device_id} => vec![UNIVERSAL_NON_REAL_TIME, *device_id, 0x09, 0x03],
+            MasterVolume{device_id, value} => vec![UNIVERSAL_REAL_TIME, *device_id, 0x04, 0x01, (value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8],
+            MasterFineTuning{device_id, value} => vec![UNIVERSAL_REAL_TIME, *device_id, 0x04, 0x03, (value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8],
+            MasterCoarseTuning{device_id, value} => vec![UNIVERSAL_REAL_TIME, *device_id, 0x04, 0x04, 0x00, *value],
+            IdentityRequest{device_id} => vec![UNIVERSAL_NON_REAL_TIME, *device_id, 0x06, 0x01],
+            IdentityReply{device_id, manufacturer, family, member, version} => {
+                let mut data = vec![UNIVERSAL_NON_REAL_TIME, *device_id, 0x06, 0x02];
+                data.extend(manufacturer);
+                data.extend([(family & 0x7F) as u8, (family >> 7) as u8, (member & 0x7F) as u8, (member >> 7) as u8]);
+                data.extend(version);
+                data
+            },
+            MidiTuning{realtime, device_id, sub_id, data} => {
+                let mut bytes = vec![if *realtime { UNIVERSAL_REAL_TIME } else { UNIVERSAL_NON_REAL_TIME }, *device_id, 0x08, *sub_id];
+                bytes.extend(data);
+                bytes
+            },
+            GsReset{device_id} => {
+                let mut data = vec![ROLAND_ID, *device_id, 0x42, 0x12];
+                data.extend(GS_RESET_BODY);
+                data.push(roland_checksum(&GS_RESET_BODY));
+                data
+            },
+            XgSystemOn{device_id} => vec![YAMAHA_ID, 0x10 | (device_id & 0x0F), 0x4C, 0x00, 0x00, 0x7E, 0x00],
+            Roland(message) => message.payload(),
+            Other(data) => data.clone(),
+        }
+    }
+
+    /// Builds a F0 SysEx event terminated with F7
+    pub fn to_event(&self) -> SysExEvent {
+        let mut data = self.payload();
+        data.push(0xF7);
+        SysExEvent::SysExF0{length: data.len() as u32, data}
+    }
+
+    /// GM System On addressed to every device
+    pub fn gm_system_on() -> SysExMessage {
+        SysExMessage::GmSystemOn{device_id: ALL_CALL}
+    }
+
+    /// GM2 System On addressed to every device
+    pub fn gm2_system_on() -> SysExMessage {
+        SysExMessage::Gm2SystemOn{device_id: ALL_CALL}
+    }
+
+    /// GS reset with the default device ID 0x10
+    pub fn gs_reset() -> SysExMessage {
+        SysExMessage::GsReset{device_id: 0x10}
+    }
+
+    /// XG System On for device number 0
+    pub fn xg_system_on() -> SysExMessage {
+        SysExMessage::XgSystemOn{device_id: 0}
+    }
+
+    /// Master volume addressed to every device
+    pub fn master_volume(value: u16) -> SysExMessage {
+        SysExMessage::MasterVolume{device_id: ALL_CALL, value: value & 0x3FFF}
+    }
+}
+
+/// One SysEx event of a split message
+#[derive(Debug, Clone, PartialEq)]
+pub struct SysExPacket {
+    /// Absolute tick
+    pub tick: u32,
+    /// Index in TrackChunk::events
+    pub index: usize,
+}
+
+/// A SysEx message reassembled from a F0 packet and following F7 continuation packets
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReassembledSysEx {
+    pub packets: std::vec::Vec<SysExPacket>,
+    /// Bytes between F0 and the terminating F7
+    pub payload: std::vec::Vec<u8>,
+    /// False if the track ended or another F0 started before the terminating F7
+    pub complete: bool,
+}
+
+impl ReassembledSysEx {
+    pub fn decode(&self) -> Result<SysExMessage> {
+        SysExMessage::decode_payload(&self.payload)
+    }
+
+    /// Absolute tick of the first packet
+    pub fn tick(&self) -> u32 {
+        self.packets[0].tick
+    }
+}
+
+/// Logical SysEx content of a track
+#[derive(Debug, Clone, PartialEq)]
+pub enum SysExItem {
+    Message(ReassembledSysEx),
+    /// F7 event outside a split message: bytes to be sent as they are
+    Escape{tick: u32, index: usize, data: std::vec::Vec<u8>},
+}
+
+/// Splits a SysEx payload into a F0 packet and F7 continuation packets of at most max_packet data bytes.
+/// Returns (delta time, event) pairs with interval ticks between packets. The last packet ends with F7.
+pub fn split_sysex(payload: &[u8], max_packet: usize, interval: u32) -> std::vec::Vec<(u32, SysExEvent)> {
+    let mut bytes = payload.to_vec();
+    bytes.push(0xF7);
+    bytes.chunks(max_packet.max(1)).enumerate().map(|(i, chunk)| {
+        let data = chunk.to_vec();
+        let length = data.len() as u32;
+        if i == 0 {
+            (0, SysExEvent::SysExF0{length, data})
+        } else {
+            (interval, SysExEvent::SysExF7{length, data})
+        }
+    }).collect()
+}
+
+impl crate::types::event::TrackChunk {
+    /// Reassembles split SysEx messages and separates F7 escape sequences
+    pub fn sysex_items(&self) -> std::vec::Vec<SysExItem> {
+        use crate::types::event::MidiEvent;
+
+        let mut items = std::vec::Vec::new();
+        let mut pending: Option<ReassembledSysEx> = None;
+        for (index, (tick, pair)) in self.absolute_ticks().into_iter().zip(self.events.iter()).enumerate() {
+            match pair.event() {
+                MidiEvent::SysExEvent(SysExEvent::SysExF0{data, ..}) => {
+                    if let Some(unfinished) = pending.take() {
+                        items.push(SysExItem::Message(unfinished));
+                    }
+                    let mut message = ReassembledSysEx {packets: vec![SysExPacket {tick, index}], payload: data.clone(), complete: false};
+                    if message.payload.last() == Some(&0xF7) {
+                        message.payload.pop();
+                        message.complete = true;
+                        items.push(SysExItem::Message(message));
+                    } else {
+                        pending = Some(message);
+                    }
+                },
+                MidiEvent::SysExEvent(SysExEvent::SysExF7{data, ..}) => match pending.as_mut() {
+                    Some(message) => {
+                        message.packets.push(SysExPacket {tick, index});
+                        message.payload.extend(data);
+                        if message.payload.last() == Some(&0xF7) {
+                            message.payload.pop();
+                            message.complete = true;
+                            items.extend(pending.take().map(SysExItem::Message));
+                        }
+                    },
+                    None => items.push(SysExItem::Escape {tick, index, data: data.clone()}),
+                },
+                _ => {}
+            }
+        }
+        items.extend(pending.map(SysExItem::Message));
+        items
+    }
+
+    /// Replaces split SysEx messages with a single F0 event at the tick of their first packet
+    pub fn join_sysex(&mut self) {
+        use crate::types::event::MidiEvent;
+
+        let mut events = self.to_absolute();
+        let mut removed = vec![false; events.len()];
+        for item in self.sysex_items() {
+            if let SysExItem::Message(message) = item {
+                if message.packets.len() < 2 {
+                    continue;
+                }
+                let mut data = message.payload.clone();
+                if message.complete {
+                    data.push(0xF7);
+                }
+                events[message.packets[0].index].1 = MidiEvent::SysExEvent(SysExEvent::SysExF0{length: data.len() as u32, data});
+                for packet in &message.packets[1..] {
+                    removed[packet.index] = true;
+                }
+            }
+        }
+
+        let mut flags = removed.into_iter();
+        events.retain(|_| !flags.next().unwrap_or(false));
+        *self = crate::types::event::TrackChunk::from_absolute(events);
+    }
+
+    /// Splits every complete SysEx message longer than max_packet bytes into packets interval ticks apart.
+    /// Split messages are joined first, so this can also change the packet size of existing splits.
+    /// Events are kept in time order, and EndOfTrack is moved to the last packet if that comes later.
+    pub fn resplit_sysex(&mut self, max_packet: usize, interval: u32) {
+        use crate::types::event::MidiEvent;
+
+        self.join_sysex();
+        let mut events = std::vec::Vec::new();
+        let mut end: Option<u32> = None;
+        for (tick, event) in self.to_absolute() {
+            if event.is_end_of_track() {
+                end = Some(end.map_or(tick, |e| e.max(tick)));
+                continue;
+            }
+            if let MidiEvent::SysExEvent(SysExEvent::SysExF0{data, ..}) = &event {
+                if data.len() > max_packet && data.last() == Some(&0xF7) {
+                    let mut packet_tick = tick;
+                    for (delta, packet) in split_sysex(&data[..data.len() - 1], max_packet, interval) {
+                        packet_tick = packet_tick.saturating_add(delta);
+                        events.push((packet_tick, MidiEvent::SysExEvent(packet)));
+                    }
+                    continue;
+                }
+            }
+            events.push((tick, event));
+        }
+        // Stable: packets only move later than the events that followed the SysEx
+        events.sort_by_key(|(tick, _)| *tick);
+        if let Some(end) = end {
+            let last = events.last().map_or(0, |(tick, _)| *tick);
+            events.push((end.max(last), MidiEvent::MetaEvent(crate::types::message::MetaEvent::EndOfTrack)));
+        }
+        *self = crate::types::event::TrackChunk::from_absolute(events);
+    }
+}
@@ -0,0 +1,111 @@
+use super::event::{MidiEvent, SMF};
+use super::message::MetaEvent;
+
+/// Default tempo (120 BPM) in microseconds per quarter note
+pub const DEFAULT_TEMPO: u32 = 500_000;
+
+/// Converts microseconds per quarter note to BPM
+pub fn tempo_to_bpm(tempo: u32) -> f64 {
+    60_000_000.0 / tempo as f64
+}
+
+/// Converts BPM to microseconds per quarter note
+pub fn bpm_to_tempo(bpm: f64) -> u32 {
+    (60_000_000.0 / bpm).round() as u32
+}
+
+/// SetTempo events of all tracks ordered by tick, used to convert between ticks and seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoMap {
+    resolution: u16,
+    /// (absolute tick, microseconds per quarter note). Always starts at tick 0.
+    changes: std::vec::Vec<(u32, u32)>,
+}
+
+impl TempoMap {
+    pub fn new(smf: &SMF) -> TempoMap {
+        let mut changes = std::vec::Vec::new();
+        for track in smf.tracks() {
+            for (tick, event) in track.to_absolute() {
+                if let MidiEvent::MetaEvent(MetaEvent::SetTempo{tempo}) = event {
+                    changes.push((tick, tempo));
+                }
+            }
+        }
+        TempoMap::from_changes(smf.resolution(), changes)
+    }
+
+    /// Creates a tempo map from (absolute tick, tempo) pairs.
+    /// When several tempos are set on the same tick, the last one wins.
+    pub fn from_changes(resolution: u16, changes: std::vec::Vec<(u32, u32)>) -> TempoMap {
+        let mut changes = changes;
+        changes.sort_by_key(|(tick, _)| *tick);
+
+        let mut merged: std::vec::Vec<(u32, u32)> = vec![(0, DEFAULT_TEMPO)];
+        for (tick, tempo) in changes {
+            match merged.last_mut() {
+                Some(last) if last.0 == tick => last.1 = tempo,
+                _ => merged.push((tick, tempo)),
+            }
+        }
+        TempoMap {resolution, changes: merged}
+    }
+
+    pub fn resolution(&self) -> u16 {
+        self.resolution
+    }
+
+    pub fn changes(&self) -> &[(u32, u32)] {
+        &self.changes
+    }
+
+    /// Returns the tempo in effect at the tick
+    pub fn tempo_at(&self, tick: u32) -> u32 {
+        self.changes.iter().take_while(|(t, _)| *t <= tick).last().map_or(DEFAULT_TEMPO, |(_, tempo)| *tempo)
+    }
+
+    fn seconds_per_tick(&self, tempo: u32) -> f64 {
+        tempo as f64 / 1_000_000.0 / self.resolution as f64
+    }
+
+    pub fn tick_to_seconds(&self, tick: u32) -> f64 {
+        let mut seconds = 0.0;
+        for (i, (start, tempo)) in self.changes.iter().enumerate() {
+            if *start >= tick {
+                break;
+            }
+            let end = self.changes.get(i + 1).map_or(tick, |(next, _)| (*next).min(tick));
+            seconds += (end - start) as f64 * self.seconds_per_tick(*tempo);
+        }
+        seconds
+    }
+
+    /// Returns the tick nearest to the time
+    pub fn seconds_to_tick(&self, seconds: f64) -> u32 {
+        let mut elapsed = 0.0;
+        for (i, (start, tempo)) in self.changes.iter().enumerate() {
+            let per_tick = self.seconds_per_tick(*tempo);
+            if let Some((next, _)) = self.changes.get(i + 1) {
+                let length = (next - start) as f64 * per_tick;
+                if elapsed + length <= seconds {
+                    elapsed += length;
+                    continue;
+                }
+            }
+            return start + ((seconds - elapsed).max(0.0) / per_tick).round() as u32;
+        }
+        unreachable!()
+    }
+}
+
+impl SMF {
+    pub fn tempo_map(&self) -> TempoMap {
+        TempoMap::new(self)
+    }
+
+    /// Returns the length of the file in seconds, measured to the last event of the longest track
+    pub fn duration_seconds(&self) -> f64 {
+        let end = self.tracks().iter().map(|track| track.end_tick()).max().unwrap_or(0);
+        self.tempo_map().tick_to_seconds(end)
+    }
+}
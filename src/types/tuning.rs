@@ -0,0 +1,223 @@
+use super::message::SysExEvent;
+use super::sysex::{SysExMessage, UNIVERSAL_NON_REAL_TIME};
+use crate::file::filerw::SmfError;
+
+type Result<T> = std::result::Result<T, SmfError>;
+
+/// Frequency of MIDI key 69 in 12-TET
+pub const A4_FREQUENCY: f64 = 440.0;
+
+/// Converts a frequency to a fractional MIDI key number in 12-TET (A4 = 69 = 440 Hz)
+pub fn frequency_to_key(frequency: f64) -> f64 {
+    69.0 + 12.0 * (frequency / A4_FREQUENCY).log2()
+}
+
+pub fn key_to_frequency(key: f64) -> f64 {
+    A4_FREQUENCY * 2f64.powf((key - 69.0) / 12.0)
+}
+
+/// MTS frequency data (xx yy zz): a semitone and a 14-bit fraction of a semitone above it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteTuning {
+    pub semitone: u8,
+    /// Units of 100/16384 cents
+    pub fraction: u16,
+}
+
+impl NoteTuning {
+    /// 7F 7F 7F: leave the note unchanged
+    pub const NO_CHANGE: NoteTuning = NoteTuning {semitone: 0x7F, fraction: 0x3FFF};
+
+    /// Creates from a fractional MIDI key number, clamped to the representable range
+    pub fn from_key(key: f64) -> NoteTuning {
+        let key = key.clamp(0.0, 127.0 + 16383.0 / 16384.0);
+        let semitone = key.floor();
+        let mut fraction = ((key - semitone) * 16384.0).round() as u32;
+        let mut semitone = semitone as u8;
+        if fraction >= 16384 {
+            semitone += 1;
+            fraction = 0;
+        }
+        NoteTuning {semitone, fraction: fraction as u16}
+    }
+
+    pub fn from_frequency(frequency: f64) -> NoteTuning {
+        NoteTuning::from_key(frequency_to_key(frequency))
+    }
+
+    pub fn key(&self) -> f64 {
+        self.semitone as f64 + self.fraction as f64 / 16384.0
+    }
+
+    pub fn frequency(&self) -> f64 {
+        key_to_frequency(self.key())
+    }
+
+    fn bytes(&self) -> [u8; 3] {
+        [self.semitone & 0x7F, ((self.fraction >> 7) & 0x7F) as u8, (self.fraction & 0x7F) as u8]
+    }
+
+    fn from_bytes(bytes: &[u8]) -> NoteTuning {
+        NoteTuning {semitone: bytes[0], fraction: ((bytes[1] as u16) << 7) | bytes[2] as u16}
+    }
+}
+
+/// Frequencies of all 128 keys
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+    /// None for keys that are not mapped
+    pub frequencies: std::vec::Vec<Option<f64>>,
+}
+
+impl Tuning {
+    /// 12-TET with A4 = 440 Hz
+    pub fn equal_temperament() -> Tuning {
+        Tuning {frequencies: (0..128).map(|key| Some(key_to_frequency(key as f64))).collect()}
+    }
+
+    pub fn frequency(&self, key: u8) -> Option<f64> {
+        self.frequencies.get(key as usize).copied().flatten()
+    }
+
+    /// MTS frequency data for every key. Unmapped keys are left unchanged.
+    pub fn note_tunings(&self) -> std::vec::Vec<NoteTuning> {
+        self.frequencies.iter().map(|f| f.map_or(NoteTuning::NO_CHANGE, NoteTuning::from_frequency)).collect()
+    }
+}
+
+/// MIDI Tuning Standard messages
+#[derive(Debug, Clone, PartialEq)]
+pub enum TuningMessage {
+    /// F0 7E dev 08 00 pp F7
+    BulkDumpRequest{device_id: u8, program: u8},
+    /// F0 7E dev 08 01 pp name(16) [xx yy zz]*128 checksum F7
+    BulkDump{device_id: u8, program: u8, name: String, tunings: std::vec::Vec<NoteTuning>},
+    /// F0 7F dev 08 02 pp ll [kk xx yy zz]*ll F7
+    SingleNoteTuningChange{device_id: u8, program: u8, changes: std::vec::Vec<(u8, NoteTuning)>},
+    /// F0 7E/7F dev 08 08 ff gg hh ss*12 F7. Offsets in cents (-64 to +63) for C to B.
+    ScaleOctave1Byte{realtime: bool, device_id: u8, channels: u16, offsets: [i8; 12]},
+    /// F0 7E/7F dev 08 09 ff gg hh [ss tt]*12 F7. Offsets in cents (-100 to +100) for C to B.
+    ScaleOctave2Byte{realtime: bool, device_id: u8, channels: u16, offsets: [f64; 12]},
+}
+
+/// Encodes a 16-bit channel mask into ff gg hh
+fn channel_bytes(channels: u16) -> [u8; 3] {
+    [((channels >> 14) & 0x03) as u8, ((channels >> 7) & 0x7F) as u8, (channels & 0x7F) as u8]
+}
+
+fn channels_from_bytes(bytes: &[u8]) -> u16 {
+    (((bytes[0] & 0x03) as u16) << 14) | (((bytes[1] & 0x7F) as u16) << 7) | (bytes[2] & 0x7F) as u16
+}
+
+/// XOR checksum of a bulk dump over every byte from the sub-ID#1 header to the last data byte
+fn bulk_dump_checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |acc, b| acc ^ b) & 0x7F
+}
+
+impl TuningMessage {
+    /// Decodes a MIDI Tuning Standard SysEx
+    pub fn decode(event: &SysExEvent) -> Result<TuningMessage> {
+        match SysExMessage::decode(event)? {
+            SysExMessage::MidiTuning{realtime, device_id, sub_id, data} => TuningMessage::decode_data(realtime, device_id, sub_id, &data),
+            _ => Err(SmfError::new("not a MIDI Tuning Standard message"))
+        }
+    }
+
+    /// Decodes the bytes following sub-ID#2
+    pub fn decode_data(realtime: bool, device_id: u8, sub_id: u8, data: &[u8]) -> Result<TuningMessage> {
+        let invalid = || SmfError::new(&format!("invalid MIDI tuning message (sub-ID 0x{:02X}, {} bytes)", sub_id, data.len()));
+        match sub_id {
+            0x00 if data.len() == 1 => Ok(TuningMessage::BulkDumpRequest{device_id, program: data[0]}),
+            0x01 if data.len() == 1 + 16 + 128 * 3 + 1 => {
+                let mut header = vec![UNIVERSAL_NON_REAL_TIME, device_id, 0x08, 0x01];
+                header.extend(&data[..data.len() - 1]);
+                let checksum = data[data.len() - 1];
+                if bulk_dump_checksum(&header) != checksum {
+                    return Err(SmfError::new("MIDI tuning bulk dump checksum mismatch"));
+                }
+                let name = String::from_utf8_lossy(&data[1..17]).trim_end().to_string();
+                let tunings = data[17..17 + 128 * 3].chunks(3).map(NoteTuning::from_bytes).collect();
+                Ok(TuningMessage::BulkDump{device_id, program: data[0], name, tunings})
+            },
+            0x02 if data.len() >= 2 && data.len() == 2 + data[1] as usize * 4 => {
+                let changes = data[2..].chunks(4).map(|c| (c[0], NoteTuning::from_bytes(&c[1..]))).collect();
+                Ok(TuningMessage::SingleNoteTuningChange{device_id, program: data[0], changes})
+            },
+            0x08 if data.len() == 3 + 12 => {
+                let mut offsets = [0i8; 12];
+                for (offset, byte) in offsets.iter_mut().zip(&data[3..]) {
+                    *offset = *byte as i8 - 64;
+                }
+                Ok(TuningMessage::ScaleOctave1Byte{realtime, device_id, channels: channels_from_bytes(data), offsets})
+            },
+            0x09 if data.len() == 3 + 24 => {
+                let mut offsets = [0.0; 12];
+                for (offset, pair) in offsets.iter_mut().zip(data[3..].chunks(2)) {
+                    let value = ((pair[0] as u16) << 7) | pair[1] as u16;
+                    *offset = (value as f64 - 8192.0) / 8192.0 * 100.0;
+                }
+                Ok(TuningMessage::ScaleOctave2Byte{realtime, device_id, channels: channels_from_bytes(data), offsets})
+            },
+            _ => Err(invalid())
+        }
+    }
+
+    pub fn to_sysex(&self) -> SysExMessage {
+        use TuningMessage::*;
+        let (realtime, device_id, sub_id, data) = match self {
+            BulkDumpRequest{device_id, program} => (false, *device_id, 0x00, vec![*program]),
+            BulkDump{device_id, program, name, tunings} => {
+                let mut data = vec![*program];
+                let mut name_bytes: std::vec::Vec<u8> = name.bytes().filter(|b| *b < 0x80).take(16).collect();
+                name_bytes.resize(16, b' ');
+                data.extend(name_bytes);
+                for i in 0..128 {
+                    data.extend(tunings.get(i).unwrap_or(&NoteTuning::NO_CHANGE).bytes());
+                }
+                let mut header = vec![UNIVERSAL_NON_REAL_TIME, *device_id, 0x08, 0x01];
+                header.extend(&data);
+                data.push(bulk_dump_checksum(&header));
+                (false, *device_id, 0x01, data)
+            },
+            SingleNoteTuningChange{device_id, program, changes} => {
+                let mut data = vec![*program, changes.len() as u8];
+                for (key, tuning) in changes {
+                    data.push(*key);
+                    data.extend(tuning.bytes());
+                }
+                (true, *device_id, 0x02, data)
+            },
+            ScaleOctave1Byte{realtime, device_id, channels, offsets} => {
+                let mut data = channel_bytes(*channels).to_vec();
+                data.extend(offsets.iter().map(|o| (o.clamp(&-64, &63) + 64) as u8));
+                (*realtime, *device_id, 0x08, data)
+            },
+            ScaleOctave2Byte{realtime, device_id, channels, offsets} => {
+                let mut data = channel_bytes(*channels).to_vec();
+                for offset in offsets {
+                    let value = ((offset / 100.0 * 8192.0).round() as i32 + 8192).clamp(0, 0x3FFF) as u16;
+                    data.extend([(value >> 7) as u8, (value & 0x7F) as u8]);
+                }
+                (*realtime, *device_id, 0x09, data)
+            },
+        };
+        SysExMessage::MidiTuning{realtime, device_id, sub_id, data}
+    }
+
+    pub fn to_event(&self) -> SysExEvent {
+        self.to_sysex().to_event()
+    }
+
+    /// Bulk dump of a tuning to the tuning program
+    pub fn bulk_dump(device_id: u8, program: u8, name: &str, tuning: &Tuning) -> TuningMessage {
+        TuningMessage::BulkDump{device_id, program, name: name.to_string(), tunings: tuning.note_tunings()}
+    }
+
+    /// Real-time single note changes of every mapped key. Returns one message per 127 keys at most.
+    pub fn single_note_changes(device_id: u8, program: u8, tuning: &Tuning) -> std::vec::Vec<TuningMessage> {
+        let changes: std::vec::Vec<(u8, NoteTuning)> = (0..128u8)
+            .filter_map(|key| tuning.frequency(key).map(|f| (key, NoteTuning::from_frequency(f))))
+            .collect();
+        changes.chunks(127).map(|chunk| TuningMessage::SingleNoteTuningChange{device_id, program, changes: chunk.to_vec()}).collect()
+    }
+}
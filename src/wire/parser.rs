@@ -0,0 +1,132 @@
+use crate::types::event::MidiEvent;
+use crate::types::message::{ChannelVoiceMessage, MidiChannelMessage, SysExEvent, SystemCommonMessage, SystemRealTimeMessage};
+
+/// Message received from a MIDI 1.0 byte stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum WireMessage {
+    Channel(MidiChannelMessage),
+    SystemCommon(SystemCommonMessage),
+    RealTime(SystemRealTimeMessage),
+    /// Complete SysEx. Data ends with F7, as in SMF.
+    SysEx(SysExEvent),
+}
+
+impl WireMessage {
+    /// Returns the event to record into an SMF track. System Common and Real-Time messages have no SMF form.
+    pub fn to_midi_event(&self) -> Option<MidiEvent> {
+        match self {
+            WireMessage::Channel(message) => Some(MidiEvent::MidiChannelMessage(message.clone())),
+            WireMessage::SysEx(event) => Some(MidiEvent::SysExEvent(event.clone())),
+            _ => None
+        }
+    }
+}
+
+/// Number of data bytes following a channel or System Common status byte
+fn data_length(status: u8) -> usize {
+    match status {
+        0xC0..=0xDF | 0xF1 | 0xF3 => 1,
+        0x80..=0xEF | 0xF2 => 2,
+        _ => 0
+    }
+}
+
+/// Builds a complete channel or System Common message
+fn decode(status: u8, data: &[u8]) -> Option<WireMessage> {
+    use ChannelVoiceMessage::*;
+    let channel = status & 0x0F;
+    let cvm = match status & 0xF0 {
+        0x80 => NoteOff{channel, key: data[0], vel: data[1]},
+        0x90 => NoteOn{channel, key: data[0], vel: data[1]},
+        0xA0 => PolyphonicKeyPressure{channel, key: data[0], vel: data[1]},
+        0xB0 => ControlChange{channel, cc: data[0], value: data[1]},
+        0xC0 => ProgramChange{channel, pc: data[0]},
+        0xD0 => ChannelKeyPressure{channel, vel: data[0]},
+        0xE0 => PitchBend{channel, lsb: data[0], msb: data[1]},
+        _ => {
+            let common = match status {
+                0xF1 => SystemCommonMessage::MtcQuarterFrame{message_type: (data[0] >> 4) & 0x07, value: data[0] & 0x0F},
+                0xF2 => SystemCommonMessage::SongPosition{beats: ((data[1] as u16) << 7) | data[0] as u16},
+                0xF3 => SystemCommonMessage::SongSelect{song: data[0]},
+                0xF6 => SystemCommonMessage::TuneRequest,
+                _ => return None
+            };
+            return Some(WireMessage::SystemCommon(common));
+        }
+    };
+    Some(WireMessage::Channel(MidiChannelMessage::ChannelVoiceMessage(cvm)))
+}
+
+/// Incremental parser of raw MIDI 1.0 data, as received from a serial port, pipe or socket.
+/// Bytes may be fed in chunks of any size; messages are returned as soon as they are complete.
+/// Real-Time bytes are returned immediately even in the middle of another message.
+/// A SysEx interrupted by another status byte is discarded, and stray data bytes are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct WireParser {
+    /// Channel status reused by messages without a status byte
+    running_status: Option<u8>,
+    /// Status of the message being received
+    status: Option<u8>,
+    data: std::vec::Vec<u8>,
+    /// SysEx being received, from the byte after F0
+    sysex: Option<std::vec::Vec<u8>>,
+}
+
+impl WireParser {
+    pub fn new() -> WireParser {
+        WireParser::default()
+    }
+
+    /// Forgets partial messages and the running status
+    pub fn reset(&mut self) {
+        *self = WireParser::new();
+    }
+
+    /// Parses every byte and returns the messages completed
+    pub fn parse(&mut self, bytes: &[u8]) -> std::vec::Vec<WireMessage> {
+        bytes.iter().filter_map(|byte| self.push(*byte)).collect()
+    }
+
+    /// Parses a byte. Returns a message if the byte completed one.
+    pub fn push(&mut self, byte: u8) -> Option<WireMessage> {
+        match byte {
+            0xF8..=0xFF => SystemRealTimeMessage::from_status_byte(byte).map(WireMessage::RealTime),
+            0xF7 => {
+                self.status = None;
+                self.running_status = None;
+                self.sysex.take().map(|mut data| {
+                    data.push(0xF7);
+                    WireMessage::SysEx(SysExEvent::SysExF0{length: data.len() as u32, data})
+                })
+            },
+            0x80..=0xF6 => {
+                self.sysex = if byte == 0xF0 { Some(vec![]) } else { None };
+                self.data.clear();
+                // System Common messages and SysEx cancel the running status
+                self.running_status = if byte < 0xF0 { Some(byte) } else { None };
+                self.status = if byte == 0xF0 { None } else { Some(byte) };
+                if data_length(byte) == 0 {
+                    self.status = None;
+                    return decode(byte, &[]);
+                }
+                None
+            },
+            _ => {
+                if let Some(sysex) = &mut self.sysex {
+                    sysex.push(byte);
+                    return None;
+                }
+                let status = self.status.or(self.running_status)?;
+                self.status = Some(status);
+                self.data.push(byte);
+                if self.data.len() < data_length(status) {
+                    return None;
+                }
+                self.status = None;
+                let message = decode(status, &self.data);
+                self.data.clear();
+                message
+            }
+        }
+    }
+}